@@ -0,0 +1,41 @@
+extern crate futures;
+extern crate libudev;
+extern crate tokio;
+
+use std::io;
+
+use futures::{future, StreamExt};
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+    let context = libudev::Context::new().unwrap();
+    monitor(&runtime, &context).unwrap();
+}
+
+fn monitor(runtime: &tokio::runtime::Runtime, context: &libudev::Context) -> io::Result<()> {
+    let mut monitor = try!(libudev::Monitor::new(context));
+
+    try!(monitor.match_subsystem_devtype("usb", "usb_device"));
+
+    let _guard = runtime.enter();
+    let events = try!(libudev::MonitorStream::new(try!(monitor.listen())));
+
+    runtime.block_on(events.for_each(|event| {
+        match event {
+            Ok(event) => {
+                println!("{}: {} {} (subsystem={}, sysname={}, devtype={})",
+                         event.sequence_number(),
+                         event.event_type(),
+                         event.syspath().map_or("", |s| { s.to_str().unwrap_or("") }),
+                         event.subsystem().map_or("", |s| { s.to_str().unwrap_or("") }),
+                         event.sysname().map_or("", |s| { s.to_str().unwrap_or("") }),
+                         event.devtype().map_or("", |s| { s.to_str().unwrap_or("") }));
+            },
+            Err(err) => println!("error: {}", err),
+        }
+
+        future::ready(())
+    }));
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+extern crate libudev;
+extern crate mio;
+
+use std::io;
+
+use mio::{Events, Interest, Poll, Token};
+
+const MONITOR: Token = Token(0);
+
+fn main() {
+    let context = libudev::Context::new().unwrap();
+    monitor(&context).unwrap();
+}
+
+fn monitor(context: &libudev::Context) -> io::Result<()> {
+    let mut monitor = try!(libudev::Monitor::new(context));
+
+    try!(monitor.match_subsystem_devtype("usb", "usb_device"));
+    let mut socket = try!(monitor.listen());
+
+    let mut poll = try!(Poll::new());
+    let mut events = Events::with_capacity(16);
+
+    try!(poll.registry().register(&mut socket, MONITOR, Interest::READABLE));
+
+    loop {
+        try!(poll.poll(&mut events, None));
+
+        for _ in events.iter().filter(|e| e.token() == MONITOR) {
+            for event in socket.drain() {
+                match event {
+                    Ok(event) => {
+                        println!("{}: {} {} (subsystem={}, sysname={}, devtype={})",
+                                 event.sequence_number(),
+                                 event.event_type(),
+                                 event.syspath().map_or("", |s| { s.to_str().unwrap_or("") }),
+                                 event.subsystem().map_or("", |s| { s.to_str().unwrap_or("") }),
+                                 event.sysname().map_or("", |s| { s.to_str().unwrap_or("") }),
+                                 event.devtype().map_or("", |s| { s.to_str().unwrap_or("") }));
+                    },
+                    Err(err) => println!("error: {}", err),
+                }
+            }
+        }
+    }
+}
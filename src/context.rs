@@ -1,5 +1,10 @@
 use ::handle::Handle;
 
+pub unsafe fn from_raw(udev: *mut ::ffi::udev) -> Context {
+    Context { udev: udev }
+}
+
+
 /// A libudev context. Contexts may not be sent or shared between threads. The `libudev(3)` manpage
 /// says:
 ///
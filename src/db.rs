@@ -0,0 +1,489 @@
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+use libc::dev_t;
+
+use ::device::Device;
+
+
+/// The database in which udev stores the state of the devices that it has processed.
+///
+/// udev keeps a record for each device in the `data` directory below its runtime directory, which
+/// is `/run/udev` by default. A `Database` can also read a copy of that directory, for example to
+/// inspect the state of another machine.
+///
+/// ## Example
+///
+/// ```no_run
+/// let database = libudev::Database::with_root("/tmp/run-udev");
+///
+/// for record in database.records().unwrap() {
+///     let (id, record) = record.unwrap();
+///     println!("{:?}: {:?}", id, record.devlinks());
+/// }
+/// ```
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Database {
+    root: PathBuf,
+}
+
+impl Database {
+    /// Returns the database of the running system in `/run/udev`.
+    pub fn new() -> Self {
+        Database::with_root("/run/udev")
+    }
+
+    /// Returns a database whose runtime directory is `root`.
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        Database { root: root.as_ref().to_path_buf() }
+    }
+
+    /// Returns the runtime directory of the database.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Reads the record of the device with the given ID.
+    ///
+    /// Device IDs have the form `b8:0` or `c4:64` for block and character devices, `n3` for network
+    /// interfaces, `+drivers:subsystem:sysname` for drivers, and `+subsystem:sysname` for other
+    /// devices. Returns `Ok(None)` if the database
+    /// doesn't contain a record for the device.
+    pub fn record<T: AsRef<OsStr>>(&self, id: T) -> ::Result<Option<DeviceRecord>> {
+        match DeviceRecord::read(self.root.join("data").join(id.as_ref())) {
+            Ok(record) => Ok(Some(record)),
+            Err(ref err) if err.kind() == ::ErrorKind::Io(io::ErrorKind::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads the record of a device.
+    ///
+    /// Returns `Ok(None)` if the database doesn't contain a record for the device.
+    pub fn device_record(&self, device: &Device) -> ::Result<Option<DeviceRecord>> {
+        let ifindex = device.property_value("IFINDEX").and_then(|ifindex| ifindex.to_str()).and_then(|ifindex| ifindex.parse().ok());
+
+        match device_id(device.subsystem(), device.sysname(), device.devpath(), device.devnum(), ifindex) {
+            Some(id) => self.record(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an iterator over the IDs and records of all devices in the database.
+    pub fn records(&self) -> ::Result<Records> {
+        match fs::read_dir(self.root.join("data")) {
+            Ok(entries) => Ok(Records { entries: entries }),
+            Err(err) => Err(::error::from_io_error(err)),
+        }
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Database::new()
+    }
+}
+
+/// Returns the ID of a device's record in udev's database.
+///
+/// The devpath is only used for drivers, whose ID includes the subsystem that the driver belongs
+/// to, like in `+drivers:usb:hub` for the devpath `/bus/usb/drivers/hub`.
+pub fn device_id(subsystem: Option<&OsStr>, sysname: Option<&OsStr>, devpath: Option<&OsStr>, devnum: Option<dev_t>, ifindex: Option<u32>) -> Option<OsString> {
+    if let Some(devnum) = devnum.filter(|&devnum| devnum != 0) {
+        let kind = if subsystem == Some(OsStr::new("block")) { 'b' } else { 'c' };
+
+        Some(OsString::from(format!("{}{}:{}", kind, ::libc::major(devnum), ::libc::minor(devnum))))
+    }
+    else if let Some(ifindex) = ifindex {
+        Some(OsString::from(format!("n{}", ifindex)))
+    }
+    else {
+        match (subsystem, sysname) {
+            (Some(subsystem), Some(sysname)) => {
+                let mut id = OsString::from("+");
+                id.push(subsystem);
+                id.push(":");
+
+                if subsystem == OsStr::new("drivers") {
+                    id.push(devpath.and_then(driver_subsystem)?);
+                    id.push(":");
+                }
+
+                id.push(sysname);
+
+                Some(id)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Returns the subsystem of a driver, which is the name of the directory that contains the
+/// `drivers` directory in its devpath.
+fn driver_subsystem(devpath: &OsStr) -> Option<&OsStr> {
+    let components: Vec<&OsStr> = Path::new(devpath).iter().collect();
+
+    components.windows(2).find(|pair| pair[1] == OsStr::new("drivers") && pair[0] != OsStr::new("/")).map(|pair| pair[0])
+}
+
+
+/// An iterator over the records in a udev database.
+pub struct Records {
+    entries: fs::ReadDir,
+}
+
+impl Iterator for Records {
+    type Item = ::Result<(OsString, DeviceRecord)>;
+
+    fn next(&mut self) -> Option<::Result<(OsString, DeviceRecord)>> {
+        let entry = match self.entries.next() {
+            Some(Ok(entry)) => entry,
+            Some(Err(err)) => return Some(Err(::error::from_io_error(err))),
+            None => return None,
+        };
+
+        Some(DeviceRecord::read(entry.path()).map(|record| (entry.file_name(), record)))
+    }
+}
+
+
+/// A device's record in udev's database.
+///
+/// A record consists of lines that start with a key and a colon:
+///
+/// * `S:` a devlink, relative to `/dev`.
+/// * `L:` the priority of the devlinks.
+/// * `W:` the watch handle of the device node.
+/// * `I:` the time at which the device was initialized, in microseconds of `CLOCK_MONOTONIC`.
+/// * `E:` a property in the form `KEY=VALUE`.
+/// * `G:` a tag.
+/// * `Q:` a current tag.
+///
+/// Lines with other keys, such as `V:` for the version of the format, are preserved. Writing a
+/// record produces lines in the same order as udev, so a record that was written by udev is
+/// reproduced exactly.
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct DeviceRecord {
+    devlinks: Vec<PathBuf>,
+    devlink_priority: Option<i32>,
+    watch_handle: Option<i32>,
+    usec_initialized: Option<u64>,
+    properties: Vec<(OsString, OsString)>,
+    tags: Vec<OsString>,
+    current_tags: Vec<OsString>,
+    other: Vec<(char, OsString)>,
+}
+
+impl DeviceRecord {
+    /// Creates an empty record.
+    pub fn new() -> Self {
+        DeviceRecord::default()
+    }
+
+    /// Parses a record from the contents of a database file.
+    ///
+    /// Returns an error with kind `ErrorKind::Parse` if a line is malformed.
+    pub fn parse(data: &[u8]) -> ::Result<Self> {
+        DeviceRecord::parse_file(data, None)
+    }
+
+    fn parse_file(data: &[u8], path: Option<&Path>) -> ::Result<Self> {
+        let line_error = |i: usize, line: &[u8], reason: &str| {
+            let record = match path {
+                Some(path) => format!("udev database record {:?}", path),
+                None => String::from("udev database record"),
+            };
+
+            ::error::parse_error(format!("invalid line {} {:?} in {}: {}", i + 1, String::from_utf8_lossy(line), record, reason))
+        };
+
+        let mut record = DeviceRecord::new();
+
+        for (i, line) in data.split(|&b| b == b'\n').enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.len() < 2 || line[1] != b':' || !line[0].is_ascii_alphabetic() {
+                return Err(line_error(i, line, "expected a key and a colon"));
+            }
+
+            let value = &line[2..];
+
+            match line[0] {
+                b'S' => record.devlinks.push(PathBuf::from(OsStr::from_bytes(value))),
+                b'L' => record.devlink_priority = Some(try!(parse_number(value).ok_or_else(|| line_error(i, line, "expected a number")))),
+                b'W' => record.watch_handle = Some(try!(parse_number(value).ok_or_else(|| line_error(i, line, "expected a number")))),
+                b'I' => record.usec_initialized = Some(try!(parse_number(value).ok_or_else(|| line_error(i, line, "expected a number")))),
+                b'E' => {
+                    match value.iter().position(|&b| b == b'=') {
+                        Some(n) => record.properties.push((bytes_to_os_string(&value[..n]), bytes_to_os_string(&value[n + 1..]))),
+                        None => return Err(line_error(i, line, "expected a property in the form KEY=VALUE")),
+                    }
+                },
+                b'G' => record.tags.push(bytes_to_os_string(value)),
+                b'Q' => record.current_tags.push(bytes_to_os_string(value)),
+                key => record.other.push((key as char, bytes_to_os_string(value))),
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Reads a record from a database file.
+    pub fn read<P: AsRef<Path>>(path: P) -> ::Result<Self> {
+        match fs::read(path.as_ref()) {
+            Ok(data) => DeviceRecord::parse_file(&data, Some(path.as_ref())),
+            Err(err) => Err(::error::from_io_error(err)),
+        }
+    }
+
+    /// Writes the record in the format of a database file.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for devlink in &self.devlinks {
+            try!(write_line(&mut writer, 'S', devlink.as_os_str().as_bytes()));
+        }
+
+        if let Some(priority) = self.devlink_priority {
+            try!(write_line(&mut writer, 'L', priority.to_string().as_bytes()));
+        }
+
+        if let Some(handle) = self.watch_handle {
+            try!(write_line(&mut writer, 'W', handle.to_string().as_bytes()));
+        }
+
+        if let Some(usec) = self.usec_initialized {
+            try!(write_line(&mut writer, 'I', usec.to_string().as_bytes()));
+        }
+
+        for &(ref key, ref value) in &self.properties {
+            let mut property = key.as_bytes().to_vec();
+            property.push(b'=');
+            property.extend_from_slice(value.as_bytes());
+
+            try!(write_line(&mut writer, 'E', &property));
+        }
+
+        for tag in &self.tags {
+            try!(write_line(&mut writer, 'G', tag.as_bytes()));
+        }
+
+        for tag in &self.current_tags {
+            try!(write_line(&mut writer, 'Q', tag.as_bytes()));
+        }
+
+        for &(key, ref value) in &self.other {
+            try!(write_line(&mut writer, key, value.as_bytes()));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the record in the format of a database file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).expect("writing to a Vec can't fail");
+        bytes
+    }
+
+    /// Returns the device's devlinks, relative to `/dev`.
+    pub fn devlinks(&self) -> &[PathBuf] {
+        &self.devlinks
+    }
+
+    /// Returns a mutable reference to the device's devlinks.
+    pub fn devlinks_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.devlinks
+    }
+
+    /// Returns the priority of the device's devlinks.
+    pub fn devlink_priority(&self) -> Option<i32> {
+        self.devlink_priority
+    }
+
+    /// Sets the priority of the device's devlinks.
+    pub fn set_devlink_priority(&mut self, priority: Option<i32>) {
+        self.devlink_priority = priority;
+    }
+
+    /// Returns the inotify watch handle of the device node.
+    pub fn watch_handle(&self) -> Option<i32> {
+        self.watch_handle
+    }
+
+    /// Sets the inotify watch handle of the device node.
+    pub fn set_watch_handle(&mut self, handle: Option<i32>) {
+        self.watch_handle = handle;
+    }
+
+    /// Returns the time at which the device was initialized, in microseconds of
+    /// `CLOCK_MONOTONIC`.
+    pub fn usec_initialized(&self) -> Option<u64> {
+        self.usec_initialized
+    }
+
+    /// Sets the time at which the device was initialized.
+    pub fn set_usec_initialized(&mut self, usec: Option<u64>) {
+        self.usec_initialized = usec;
+    }
+
+    /// Returns the properties that were set by udev's rules, in the order of the record.
+    pub fn properties(&self) -> &[(OsString, OsString)] {
+        &self.properties
+    }
+
+    /// Returns a mutable reference to the device's properties.
+    pub fn properties_mut(&mut self) -> &mut Vec<(OsString, OsString)> {
+        &mut self.properties
+    }
+
+    /// Returns the value of the given property.
+    pub fn property_value<T: AsRef<OsStr>>(&self, property: T) -> Option<&OsStr> {
+        let property = property.as_ref();
+
+        self.properties.iter().rev().find(|&&(ref key, _)| key == property).map(|&(_, ref value)| value.as_os_str())
+    }
+
+    /// Returns the device's tags.
+    pub fn tags(&self) -> &[OsString] {
+        &self.tags
+    }
+
+    /// Returns a mutable reference to the device's tags.
+    pub fn tags_mut(&mut self) -> &mut Vec<OsString> {
+        &mut self.tags
+    }
+
+    /// Returns the device's current tags.
+    pub fn current_tags(&self) -> &[OsString] {
+        &self.current_tags
+    }
+
+    /// Returns a mutable reference to the device's current tags.
+    pub fn current_tags_mut(&mut self) -> &mut Vec<OsString> {
+        &mut self.current_tags
+    }
+
+    /// Returns the lines with unrecognized keys as pairs of keys and values.
+    pub fn other_entries(&self) -> &[(char, OsString)] {
+        &self.other
+    }
+}
+
+fn bytes_to_os_string(bytes: &[u8]) -> OsString {
+    OsString::from_vec(bytes.to_vec())
+}
+
+fn parse_number<T: ::std::str::FromStr>(value: &[u8]) -> Option<T> {
+    ::std::str::from_utf8(value).ok().and_then(|value| value.parse().ok())
+}
+
+fn write_line<W: Write>(writer: &mut W, key: char, value: &[u8]) -> io::Result<()> {
+    try!(write!(writer, "{}:", key));
+    try!(writer.write_all(value));
+    writer.write_all(b"\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+    use std::path::PathBuf;
+
+    use super::{device_id, DeviceRecord};
+
+    const RECORD: &[u8] = b"S:disk/by-id/ata-SAMSUNG_SSD\n\
+                            S:disk/by-uuid/0f3c\n\
+                            L:-100\n\
+                            W:7\n\
+                            I:1234567890\n\
+                            E:ID_FS_TYPE=ext4\n\
+                            E:ID_FS_LABEL=root=fs\n\
+                            G:systemd\n\
+                            Q:systemd\n\
+                            V:1\n";
+
+    #[test]
+    fn parse_every_line_kind() {
+        let record = DeviceRecord::parse(RECORD).unwrap();
+
+        assert_eq!(record.devlinks(), &[PathBuf::from("disk/by-id/ata-SAMSUNG_SSD"), PathBuf::from("disk/by-uuid/0f3c")]);
+        assert_eq!(record.devlink_priority(), Some(-100));
+        assert_eq!(record.watch_handle(), Some(7));
+        assert_eq!(record.usec_initialized(), Some(1234567890));
+        assert_eq!(record.property_value("ID_FS_TYPE"), Some(OsStr::new("ext4")));
+        assert_eq!(record.property_value("ID_FS_LABEL"), Some(OsStr::new("root=fs")));
+        assert_eq!(record.tags(), &[OsString::from("systemd")]);
+        assert_eq!(record.current_tags(), &[OsString::from("systemd")]);
+        assert_eq!(record.other_entries(), &[('V', OsString::from("1"))]);
+    }
+
+    #[test]
+    fn parse_to_bytes_round_trip() {
+        let record = DeviceRecord::parse(RECORD).unwrap();
+        let bytes = record.to_bytes();
+
+        assert_eq!(&bytes[..], RECORD);
+        assert_eq!(DeviceRecord::parse(&bytes).unwrap(), record);
+    }
+
+    #[test]
+    fn parse_skips_empty_lines() {
+        let record = DeviceRecord::parse(b"\nG:seat\n\n").unwrap();
+
+        assert_eq!(record.tags(), &[OsString::from("seat")]);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lines() {
+        let lines: &[&[u8]] = &[
+            b"S",
+            b"Sdisk/by-uuid/0f3c",
+            b"1:value",
+            b":value",
+            b"L:high",
+            b"W:",
+            b"I:-1",
+            b"E:ID_FS_TYPE",
+        ];
+
+        for line in lines {
+            let err = DeviceRecord::parse(line).unwrap_err();
+
+            assert_eq!(err.kind(), ::ErrorKind::Parse, "{:?}", String::from_utf8_lossy(line));
+        }
+    }
+
+    #[test]
+    fn device_id_of_block_device() {
+        assert_eq!(device_id(Some(OsStr::new("block")), Some(OsStr::new("sda")), None, Some(::libc::makedev(8, 0)), None), Some(OsString::from("b8:0")));
+    }
+
+    #[test]
+    fn device_id_of_char_device() {
+        assert_eq!(device_id(Some(OsStr::new("tty")), Some(OsStr::new("tty0")), None, Some(::libc::makedev(4, 0)), None), Some(OsString::from("c4:0")));
+    }
+
+    #[test]
+    fn device_id_of_network_interface() {
+        assert_eq!(device_id(Some(OsStr::new("net")), Some(OsStr::new("lo")), None, None, Some(1)), Some(OsString::from("n1")));
+    }
+
+    #[test]
+    fn device_id_of_other_device() {
+        assert_eq!(device_id(Some(OsStr::new("pci")), Some(OsStr::new("0000:00:17.0")), None, Some(0), None), Some(OsString::from("+pci:0000:00:17.0")));
+        assert_eq!(device_id(None, Some(OsStr::new("0000:00:17.0")), None, None, None), None);
+    }
+
+    #[test]
+    fn device_id_of_driver() {
+        let devpath = OsStr::new("/bus/usb/drivers/hub");
+
+        assert_eq!(device_id(Some(OsStr::new("drivers")), Some(OsStr::new("hub")), Some(devpath), None, None), Some(OsString::from("+drivers:usb:hub")));
+        assert_eq!(device_id(Some(OsStr::new("drivers")), Some(OsStr::new("hub")), None, None, None), None);
+    }
+}
@@ -1,13 +1,24 @@
+use std::cmp;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ptr;
 use std::str;
 
 use std::ffi::{CStr, OsStr};
+use std::fs::File;
+use std::io::Read;
 use std::marker::PhantomData;
-use std::path::Path;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Component, Path, PathBuf};
+use std::result::Result as StdResult;
 use std::str::FromStr;
+use std::time::Duration;
 
 use libc::{c_char, dev_t};
 
 use ::context::Context;
+use ::enumerator::Enumerator;
 use ::handle::Handle;
 
 
@@ -17,6 +28,24 @@ pub unsafe fn from_raw(device: *mut ::ffi::udev_device) -> Device {
     Device { device: device }
 }
 
+pub unsafe fn properties_from_raw<'a>(entry: *mut ::ffi::udev_list_entry) -> Properties<'a> {
+    Properties {
+        _device: PhantomData,
+        entry: entry,
+    }
+}
+
+
+/// Types of device nodes.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum DeviceType {
+    /// A character device.
+    Character,
+
+    /// A block device.
+    Block,
+}
+
 
 /// A structure that provides access to sysfs/kernel devices.
 pub struct Device {
@@ -56,6 +85,99 @@ impl Device {
         })
     }
 
+    /// Creates a device for a given device type and device number.
+    ///
+    /// The device number is the major/minor number of a device node, e.g., the `st_rdev` field
+    /// returned by `stat()` for a device node.
+    pub fn from_devnum(context: &Context, device_type: DeviceType, devnum: dev_t) -> ::Result<Self> {
+        let device_type = match device_type {
+            DeviceType::Character => b'c',
+            DeviceType::Block => b'b',
+        };
+
+        Ok(unsafe {
+            from_raw(try_alloc!(
+                ::ffi::udev_device_new_from_devnum(context.as_ptr(), device_type as c_char, devnum)
+            ))
+        })
+    }
+
+    /// Creates a device for a given subsystem and sysname.
+    ///
+    /// For example, the device for the network interface `eth0` can be created with a subsystem of
+    /// `net` and a sysname of `eth0`.
+    pub fn from_subsystem_sysname<T: AsRef<OsStr>, U: AsRef<OsStr>>(context: &Context, subsystem: T, sysname: U) -> ::Result<Self> {
+        let subsystem = try!(::util::os_str_to_cstring(subsystem));
+        let sysname = try!(::util::os_str_to_cstring(sysname));
+
+        Ok(unsafe {
+            from_raw(try_alloc!(
+                ::ffi::udev_device_new_from_subsystem_sysname(context.as_ptr(), subsystem.as_ptr(), sysname.as_ptr())
+            ))
+        })
+    }
+
+    /// Creates a device for a given device ID.
+    ///
+    /// A device ID is a string that uniquely identifies a device, in one of the following formats:
+    ///
+    /// * `b8:0` for a block device with the major/minor number 8:0.
+    /// * `c128:1` for a character device with the major/minor number 128:1.
+    /// * `n3` for the network interface with the index 3.
+    /// * `+usb:1-1` for the device with the subsystem `usb` and the sysname `1-1`.
+    pub fn from_device_id<T: AsRef<OsStr>>(context: &Context, id: T) -> ::Result<Self> {
+        let id = try!(::util::os_str_to_cstring(id));
+
+        Ok(unsafe {
+            from_raw(try_alloc!(
+                ::ffi::udev_device_new_from_device_id(context.as_ptr(), id.as_ptr())
+            ))
+        })
+    }
+
+    /// Creates a device for a given device node.
+    ///
+    /// The `devnode` parameter should be a path to a device node or a symlink to one, e.g.,
+    /// `/dev/ttyUSB0` or `/dev/disk/by-id/usb-SanDisk_Cruzer_4C530001-0:0`. Symlinks are resolved
+    /// and the device is looked up by the type and device number of the node. An error is returned
+    /// if the path doesn't refer to a block or character device node.
+    pub fn from_devnode(context: &Context, devnode: &Path) -> ::Result<Self> {
+        let metadata = match fs::metadata(devnode) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                return Err(::error::with_message(err.raw_os_error().unwrap_or(::libc::EIO),
+                                                 format!("{}: {}", devnode.display(), err)));
+            },
+        };
+
+        let file_type = metadata.file_type();
+
+        let device_type = if file_type.is_block_device() {
+            DeviceType::Block
+        }
+        else if file_type.is_char_device() {
+            DeviceType::Character
+        }
+        else {
+            return Err(::error::with_message(::libc::ENODEV,
+                                             format!("{} is not a device node", devnode.display())));
+        };
+
+        match Device::from_devnum(context, device_type, metadata.rdev() as dev_t) {
+            Ok(device) => Ok(device),
+            Err(err) => {
+                let errno = ::error::raw_os_error(&err);
+
+                let message = match errno {
+                    ::libc::ENODEV | ::libc::ENOENT => format!("no device found for device node {}", devnode.display()),
+                    _ => format!("{}: {}", devnode.display(), err),
+                };
+
+                Err(::error::with_message(errno, message))
+            },
+        }
+    }
+
     /// Checks whether the device has already been handled by udev.
     ///
     /// When a new device is connected to the system, udev initializes the device by setting
@@ -70,6 +192,37 @@ impl Device {
         }
     }
 
+    /// Returns the time that has passed since udev finished initializing the device.
+    ///
+    /// This can be used to ignore devices that were only just initialized, e.g., to debounce
+    /// hardware that repeatedly connects and disconnects. Returns `None` if the device hasn't been
+    /// initialized by udev.
+    pub fn usec_since_initialized(&self) -> Option<Duration> {
+        match unsafe { ::ffi::udev_device_get_usec_since_initialized(self.device) } {
+            0 => None,
+            usec => Some(Duration::from_micros(usec)),
+        }
+    }
+
+    /// Returns the action of the event that the device was received with, e.g., `add` or `bind`.
+    ///
+    /// Devices that weren't received from a `MonitorSocket` don't have an action.
+    pub fn action(&self) -> Option<&OsStr> {
+        ::util::ptr_to_os_str(unsafe {
+            ::ffi::udev_device_get_action(self.device)
+        })
+    }
+
+    /// Returns the sequence number of the event that the device was received with.
+    ///
+    /// Devices that weren't received from a `MonitorSocket` don't have a sequence number.
+    pub fn seqnum(&self) -> Option<u64> {
+        match unsafe { ::ffi::udev_device_get_seqnum(self.device) } {
+            0 => None,
+            n => Some(n),
+        }
+    }
+
     /// Gets the device's major/minor number.
     pub fn devnum(&self) -> Option<dev_t> {
         match unsafe { ::ffi::udev_device_get_devnum(self.device) } {
@@ -125,6 +278,155 @@ impl Device {
         }
     }
 
+    /// Returns the closest ancestor of the device that belongs to the given subsystem.
+    pub fn parent_with_subsystem<T: AsRef<OsStr>>(&self, subsystem: T) -> Option<Device> {
+        let subsystem = match ::util::os_str_to_cstring(subsystem) {
+            Ok(subsystem) => subsystem,
+            Err(_) => return None,
+        };
+
+        let ptr = unsafe {
+            ::ffi::udev_device_get_parent_with_subsystem_devtype(self.device, subsystem.as_ptr(), ptr::null())
+        };
+
+        if !ptr.is_null() {
+            unsafe {
+                ::ffi::udev_device_ref(ptr);
+
+                Some(from_raw(ptr))
+            }
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns the closest ancestor of the device that belongs to the given subsystem and has the
+    /// given device type.
+    ///
+    /// For example, the USB device that a `ttyUSB0` serial port belongs to is the ancestor with the
+    /// subsystem `usb` and the device type `usb_device`.
+    pub fn parent_with_subsystem_devtype<T: AsRef<OsStr>, U: AsRef<OsStr>>(&self, subsystem: T, devtype: U) -> Option<Device> {
+        let subsystem = match ::util::os_str_to_cstring(subsystem) {
+            Ok(subsystem) => subsystem,
+            Err(_) => return None,
+        };
+
+        let devtype = match ::util::os_str_to_cstring(devtype) {
+            Ok(devtype) => devtype,
+            Err(_) => return None,
+        };
+
+        let ptr = unsafe {
+            ::ffi::udev_device_get_parent_with_subsystem_devtype(self.device, subsystem.as_ptr(), devtype.as_ptr())
+        };
+
+        if !ptr.is_null() {
+            unsafe {
+                ::ffi::udev_device_ref(ptr);
+
+                Some(from_raw(ptr))
+            }
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the ancestors of the device.
+    ///
+    /// The iterator starts with the device's parent and walks up the device tree until it reaches
+    /// a device without a parent. The device itself is not included.
+    ///
+    /// ## Example
+    ///
+    /// This example prints out the subsystems of all of a device's ancestors:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/class/tty/ttyUSB0")).unwrap();
+    /// for ancestor in device.ancestors() {
+    ///     println!("{:?} ({:?})", ancestor.syspath(), ancestor.subsystem());
+    /// }
+    /// ```
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    /// Returns an iterator over the device's children.
+    ///
+    /// The children of a device are the devices whose parent is the device.
+    ///
+    /// ## Example
+    ///
+    /// This example prints out all of a USB device's interfaces:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/bus/usb/devices/1-1")).unwrap();
+    /// for interface in device.children().unwrap() {
+    ///     println!("{:?}", interface.syspath());
+    /// }
+    /// ```
+    pub fn children(&self) -> ::Result<Children> {
+        Ok(Children {
+            descendants: try!(self.descendants()),
+        })
+    }
+
+    /// Returns an iterator over the device's children that belong to the given subsystem.
+    pub fn children_with_subsystem<T: AsRef<OsStr>>(&self, subsystem: T) -> ::Result<Children> {
+        Ok(Children {
+            descendants: try!(self.descendants_with_subsystem(subsystem)),
+        })
+    }
+
+    /// Returns an iterator over the device's descendants.
+    ///
+    /// The descendants of a device are all devices in the subtree below the device, not including
+    /// the device itself. The descendants are sorted in dependency order.
+    ///
+    /// ## Example
+    ///
+    /// This example prints out all partitions of a disk:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/class/block/sda")).unwrap();
+    /// for partition in device.descendants().unwrap() {
+    ///     println!("{:?}", partition.devnode());
+    /// }
+    /// ```
+    pub fn descendants(&self) -> ::Result<Descendants> {
+        let enumerator = try!(self.enumerator());
+
+        Descendants::new(self, enumerator)
+    }
+
+    /// Returns an iterator over the device's descendants that belong to the given subsystem.
+    pub fn descendants_with_subsystem<T: AsRef<OsStr>>(&self, subsystem: T) -> ::Result<Descendants> {
+        let mut enumerator = try!(self.enumerator());
+        try!(enumerator.match_subsystem(subsystem));
+
+        Descendants::new(self, enumerator)
+    }
+
+    fn enumerator(&self) -> ::Result<Enumerator> {
+        let context = unsafe {
+            ::context::from_raw(::ffi::udev_ref(::ffi::udev_device_get_udev(self.device)))
+        };
+
+        let mut enumerator = try!(Enumerator::new(&context));
+        try!(enumerator.match_parent(self));
+
+        Ok(enumerator)
+    }
+
     /// Returns the subsystem name of the device.
     ///
     /// The subsystem name is a string that indicates which kernel subsystem the device belongs to.
@@ -178,6 +480,11 @@ impl Device {
         ::util::ptr_to_os_str(unsafe { ::ffi::udev_device_get_driver(self.device) })
     }
 
+    /// Checks whether a kernel driver is bound to the device.
+    pub fn is_bound(&self) -> bool {
+        self.driver().is_some()
+    }
+
     /// Retrieves the value of a device property.
     pub fn property_value<T: AsRef<OsStr>>(&self, property: T) -> Option<&OsStr> {
         match ::util::os_str_to_cstring(property) {
@@ -203,6 +510,111 @@ impl Device {
         }
     }
 
+    /// Retrieves the value of a device property and parses it.
+    ///
+    /// Surrounding whitespace is removed from the value before it's parsed. Returns `Ok(None)` if the
+    /// device doesn't have the property and an error of kind `ErrorKind::Parse` if the value can't
+    /// be parsed as `T`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/class/block/sda")).unwrap();
+    /// let major = device.property_as::<u32>("MAJOR").unwrap();
+    /// ```
+    pub fn property_as<T: FromStr>(&self, property: impl AsRef<OsStr>) -> ::Result<Option<T>> where T::Err: fmt::Display {
+        let property = property.as_ref();
+
+        self.parse_value("property", property, self.property_value(property), |value| {
+            value.parse().map_err(|err: T::Err| err.to_string())
+        })
+    }
+
+    /// Retrieves the value of a device property and parses it as a hexadecimal `u16`.
+    ///
+    /// The value may optionally start with `0x`. This is useful for properties like `ID_VENDOR_ID`.
+    pub fn property_hex_u16<T: AsRef<OsStr>>(&self, property: T) -> ::Result<Option<u16>> {
+        let property = property.as_ref();
+
+        self.parse_value("property", property, self.property_value(property), parse_hex_u16)
+    }
+
+    /// Retrieves the value of a device property and parses it as a boolean.
+    ///
+    /// The values `1`, `Y`, and `y` are parsed as `true`, and the values `0`, `N`, and `n` are parsed
+    /// as `false`.
+    pub fn property_bool<T: AsRef<OsStr>>(&self, property: T) -> ::Result<Option<bool>> {
+        let property = property.as_ref();
+
+        self.parse_value("property", property, self.property_value(property), parse_bool)
+    }
+
+    /// Retrieves the value of a device attribute and parses it.
+    ///
+    /// Surrounding whitespace is removed from the value before it's parsed. Returns `Ok(None)` if the
+    /// device doesn't have the attribute and an error of kind `ErrorKind::Parse` if the value can't
+    /// be parsed as `T`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/class/block/sda")).unwrap();
+    /// let sectors = device.attribute_as::<u64>("size").unwrap();
+    /// ```
+    pub fn attribute_as<T: FromStr>(&self, attribute: impl AsRef<OsStr>) -> ::Result<Option<T>> where T::Err: fmt::Display {
+        let attribute = attribute.as_ref();
+
+        self.parse_value("attribute", attribute, self.attribute_value(attribute), |value| {
+            value.parse().map_err(|err: T::Err| err.to_string())
+        })
+    }
+
+    /// Retrieves the value of a device attribute and parses it as a hexadecimal `u16`.
+    ///
+    /// The value may optionally start with `0x`. This is useful for attributes like `idVendor` and
+    /// `idProduct` of USB devices.
+    pub fn attribute_hex_u16<T: AsRef<OsStr>>(&self, attribute: T) -> ::Result<Option<u16>> {
+        let attribute = attribute.as_ref();
+
+        self.parse_value("attribute", attribute, self.attribute_value(attribute), parse_hex_u16)
+    }
+
+    /// Retrieves the value of a device attribute and parses it as a boolean.
+    ///
+    /// The values `1`, `Y`, and `y` are parsed as `true`, and the values `0`, `N`, and `n` are parsed
+    /// as `false`. This is useful for attributes like `removable`.
+    pub fn attribute_bool<T: AsRef<OsStr>>(&self, attribute: T) -> ::Result<Option<bool>> {
+        let attribute = attribute.as_ref();
+
+        self.parse_value("attribute", attribute, self.attribute_value(attribute), parse_bool)
+    }
+
+    fn parse_value<T, F>(&self, kind: &str, name: &OsStr, value: Option<&OsStr>, parse: F) -> ::Result<Option<T>>
+        where F: FnOnce(&str) -> StdResult<T, String>
+    {
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        match parse_os_str(value, parse) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => {
+                let device = match self.syspath() {
+                    Some(syspath) => syspath.display().to_string(),
+                    None => String::from("unknown device"),
+                };
+
+                Err(::error::parse_error(format!("invalid value {:?} for {} {:?} of {}: {}",
+                                                 value, kind, name, device, err)))
+            },
+        }
+    }
+
     /// Sets the value of a device attribute.
     pub fn set_attribute_value<T: AsRef<OsStr>, U: AsRef<OsStr>>(&mut self, attribute: T, value: U) -> ::Result<()> {
         let attribute = try!(::util::os_str_to_cstring(attribute));
@@ -213,6 +625,68 @@ impl Device {
         })
     }
 
+    /// Reads the raw contents of a device attribute.
+    ///
+    /// Unlike `attribute_value()`, this method reads the attribute's file in `sysfs` directly,
+    /// bypassing libudev's attribute cache, which truncates values and strips trailing whitespace.
+    /// This makes it suitable for binary attributes, such as the `descriptors` of a USB device, the
+    /// `config` space of a PCI device, or the `report_descriptor` of a HID device.
+    ///
+    /// The attribute name is a path relative to the device's syspath, e.g., `descriptors` or
+    /// `power/control`. An error is returned if the attribute is larger than `limit` bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/bus/usb/devices/1-1")).unwrap();
+    /// let descriptors = device.read_attribute_bytes("descriptors", 64 * 1024).unwrap();
+    /// ```
+    pub fn read_attribute_bytes<T: AsRef<OsStr>>(&self, attribute: T, limit: usize) -> ::Result<Vec<u8>> {
+        let mut reader = try!(self.attribute_reader(&attribute, limit));
+        let mut bytes = Vec::new();
+
+        match reader.read_to_end(&mut bytes) {
+            Ok(_) => Ok(bytes),
+            Err(err) => Err(reader.error(err)),
+        }
+    }
+
+    /// Opens a device attribute for reading its raw contents.
+    ///
+    /// The returned reader reads the attribute's file in `sysfs` directly, like
+    /// `read_attribute_bytes()`. Reading fails with an error if the attribute is larger than `limit`
+    /// bytes.
+    pub fn attribute_reader<T: AsRef<OsStr>>(&self, attribute: T, limit: usize) -> ::Result<AttributeReader> {
+        let attribute = Path::new(attribute.as_ref());
+
+        if !attribute.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(::error::with_message(::libc::EINVAL,
+                                             format!("invalid attribute name {:?}", attribute)));
+        }
+
+        let path = match self.syspath() {
+            Some(syspath) => syspath.join(attribute),
+            None => return Err(::error::from_raw_os_error(::libc::ENODEV)),
+        };
+
+        match File::open(&path) {
+            Ok(file) => {
+                Ok(AttributeReader {
+                    file: file,
+                    path: path,
+                    limit: limit,
+                    remaining: limit,
+                })
+            },
+            Err(err) => {
+                Err(::error::with_message(err.raw_os_error().unwrap_or(::libc::EIO),
+                                          format!("{}: {}", path.display(), err)))
+            },
+        }
+    }
+
     /// Returns an iterator over the device's properties.
     ///
     /// ## Example
@@ -254,10 +728,344 @@ impl Device {
             entry: unsafe { ::ffi::udev_device_get_sysattr_list_entry(self.device) },
         }
     }
+
+    /// Returns an iterator over the device's tags.
+    ///
+    /// Tags are attached to devices by udev rules, e.g., `uaccess` or `seat`. Once a tag has been
+    /// attached to a device, it stays attached until the device is removed, even if a later event
+    /// doesn't attach it again. `current_tags()` only returns the tags that were attached by the
+    /// most recent event.
+    ///
+    /// ## Example
+    ///
+    /// This example prints out all of a device's tags:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/devices/virtual/tty/tty0")).unwrap();
+    /// for tag in device.tags() {
+    ///     println!("{:?}", tag);
+    /// }
+    /// ```
+    pub fn tags(&self) -> Tags<'_> {
+        Tags {
+            _device: PhantomData,
+            entry: unsafe { ::ffi::udev_device_get_tags_list_entry(self.device) },
+        }
+    }
+
+    /// Returns an iterator over the tags that were attached to the device by the most recent event.
+    ///
+    /// Current tags require libudev from systemd 247 or later. With older versions, this method
+    /// returns the same tags as `tags()`.
+    pub fn current_tags(&self) -> Tags<'_> {
+        Tags {
+            _device: PhantomData,
+            entry: unsafe { ::ffi::udev_device_get_current_tags_list_entry(self.device) },
+        }
+    }
+
+    /// Checks whether the given tag is attached to the device.
+    pub fn has_tag<T: AsRef<OsStr>>(&self, tag: T) -> bool {
+        match ::util::os_str_to_cstring(tag) {
+            Ok(tag) => unsafe { ::ffi::udev_device_has_tag(self.device, tag.as_ptr()) > 0 },
+            Err(_) => false,
+        }
+    }
+
+    /// Checks whether the given tag was attached to the device by the most recent event.
+    ///
+    /// Current tags require libudev from systemd 247 or later. With older versions, this method
+    /// behaves like `has_tag()`.
+    pub fn has_current_tag<T: AsRef<OsStr>>(&self, tag: T) -> bool {
+        match ::util::os_str_to_cstring(tag) {
+            Ok(tag) => unsafe { ::ffi::udev_device_has_current_tag(self.device, tag.as_ptr()) > 0 },
+            Err(_) => false,
+        }
+    }
+
+    /// Returns an iterator over the device's devlinks.
+    ///
+    /// Devlinks are symlinks to the device node that are created by udev, e.g., the links in
+    /// `/dev/disk/by-id/`.
+    ///
+    /// ## Example
+    ///
+    /// This example prints out all of a device's devlinks:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/class/block/sda")).unwrap();
+    /// for devlink in device.devlinks() {
+    ///     println!("{:?}", devlink);
+    /// }
+    /// ```
+    pub fn devlinks(&self) -> Devlinks<'_> {
+        Devlinks {
+            _device: PhantomData,
+            entry: unsafe { ::ffi::udev_device_get_devlinks_list_entry(self.device) },
+        }
+    }
+}
+
+
+fn parse_os_str<T, F>(value: &OsStr, parse: F) -> StdResult<T, String>
+    where F: FnOnce(&str) -> StdResult<T, String>
+{
+    match value.to_str() {
+        Some(s) => parse(s.trim()),
+        None => Err(String::from("value is not valid UTF-8")),
+    }
+}
+
+fn parse_hex_u16(value: &str) -> StdResult<u16, String> {
+    let digits = if value.starts_with("0x") || value.starts_with("0X") {
+        &value[2..]
+    }
+    else {
+        value
+    };
+
+    u16::from_str_radix(digits, 16).map_err(|err| err.to_string())
+}
+
+fn parse_bool(value: &str) -> StdResult<bool, String> {
+    match value {
+        "1" | "Y" | "y" => Ok(true),
+        "0" | "N" | "n" => Ok(false),
+        _ => Err(String::from("expected 0, 1, Y, y, N, or n")),
+    }
+}
+
+
+/// Reader for the raw contents of a device attribute.
+///
+/// An `AttributeReader` is returned by `Device::attribute_reader()`.
+pub struct AttributeReader {
+    file: File,
+    path: PathBuf,
+    limit: usize,
+    remaining: usize,
+}
+
+impl AttributeReader {
+    /// Returns the path of the attribute's file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn error(&self, err: io::Error) -> ::Error {
+        let errno = err.raw_os_error().unwrap_or(::libc::EIO);
+
+        if errno == ::libc::EFBIG {
+            ::error::with_message(errno, format!("{} is larger than {} bytes", self.path.display(), self.limit))
+        }
+        else {
+            ::error::with_message(errno, format!("{}: {}", self.path.display(), err))
+        }
+    }
+}
+
+impl Read for AttributeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            // Check whether the attribute has more data than the limit allows.
+            let mut probe = [0u8; 1];
+
+            return match try!(self.file.read(&mut probe)) {
+                0 => Ok(0),
+                _ => Err(io::Error::from_raw_os_error(::libc::EFBIG)),
+            };
+        }
+
+        let len = cmp::min(buf.len(), self.remaining);
+        let n = try!(self.file.read(&mut buf[..len]));
+
+        self.remaining -= n;
+
+        Ok(n)
+    }
+}
+
+
+/// Iterator over a device's tags.
+pub struct Tags<'a> {
+    _device: PhantomData<&'a Device>,
+    entry: *mut ::ffi::udev_list_entry,
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = &'a OsStr;
+
+    fn next(&mut self) -> Option<&'a OsStr> {
+        if !self.entry.is_null() {
+            unsafe {
+                let name = ::util::ptr_to_os_str_unchecked(::ffi::udev_list_entry_get_name(self.entry));
+
+                self.entry = ::ffi::udev_list_entry_get_next(self.entry);
+
+                Some(name)
+            }
+        }
+        else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+
+/// Iterator over a device's devlinks.
+pub struct Devlinks<'a> {
+    _device: PhantomData<&'a Device>,
+    entry: *mut ::ffi::udev_list_entry,
 }
 
+impl<'a> Iterator for Devlinks<'a> {
+    type Item = &'a Path;
 
-/// Iterator over a device's properties.
+    fn next(&mut self) -> Option<&'a Path> {
+        if !self.entry.is_null() {
+            unsafe {
+                let name = ::util::ptr_to_os_str_unchecked(::ffi::udev_list_entry_get_name(self.entry));
+
+                self.entry = ::ffi::udev_list_entry_get_next(self.entry);
+
+                Some(Path::new(name))
+            }
+        }
+        else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+
+/// Iterator over a device's ancestors.
+pub struct Ancestors {
+    next: Option<Device>,
+}
+
+impl Iterator for Ancestors {
+    type Item = Device;
+
+    fn next(&mut self) -> Option<Device> {
+        let device = self.next.take();
+
+        if let Some(ref device) = device {
+            self.next = device.parent();
+        }
+
+        device
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.next {
+            Some(_) => (1, None),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+
+/// Iterator over a device's children.
+pub struct Children {
+    descendants: Descendants,
+}
+
+impl Iterator for Children {
+    type Item = Device;
+
+    fn next(&mut self) -> Option<Device> {
+        while let Some(device) = self.descendants.next() {
+            let is_child = match device.parent() {
+                Some(parent) => parent.syspath() == Some(&self.descendants.syspath),
+                None => false,
+            };
+
+            if is_child {
+                return Some(device);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+
+/// Iterator over a device's descendants.
+pub struct Descendants {
+    enumerator: Enumerator,
+    entry: *mut ::ffi::udev_list_entry,
+    syspath: PathBuf,
+}
+
+impl Descendants {
+    fn new(device: &Device, mut enumerator: Enumerator) -> ::Result<Self> {
+        let syspath = match device.syspath() {
+            Some(syspath) => syspath.to_path_buf(),
+            None => return Err(::error::from_raw_os_error(::libc::ENODEV)),
+        };
+
+        try!(enumerator.scan_devices());
+
+        let entry = unsafe { ::ffi::udev_enumerate_get_list_entry(enumerator.as_ptr()) };
+
+        Ok(Descendants {
+            enumerator: enumerator,
+            entry: entry,
+            syspath: syspath,
+        })
+    }
+}
+
+impl Iterator for Descendants {
+    type Item = Device;
+
+    fn next(&mut self) -> Option<Device> {
+        while !self.entry.is_null() {
+            unsafe {
+                let syspath = ::ffi::udev_list_entry_get_name(self.entry);
+
+                self.entry = ::ffi::udev_list_entry_get_next(self.entry);
+
+                // The enumerator includes the device itself.
+                if ::util::ptr_to_path(syspath) == Some(&self.syspath) {
+                    continue;
+                }
+
+                let udev = ::ffi::udev_enumerate_get_udev(self.enumerator.as_ptr());
+                let device = ::ffi::udev_device_new_from_syspath(udev, syspath);
+
+                if !device.is_null() {
+                    return Some(from_raw(device));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+
+/// Iterator over a device's properties or the properties of a hardware database entry.
 pub struct Properties<'a> {
     _device: PhantomData<&'a Device>,
     entry: *mut ::ffi::udev_list_entry,
@@ -356,3 +1164,99 @@ impl<'a> Attribute<'a> {
         self.device.attribute_value(self.name)
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use std::ffi::OsStr;
+    use std::io::Read;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use ::context::Context;
+
+    use super::{parse_bool, parse_hex_u16, parse_os_str, Device};
+
+    fn loopback(context: &Context) -> Device {
+        Device::from_syspath(context, Path::new("/sys/devices/virtual/net/lo")).unwrap()
+    }
+
+    #[test]
+    fn hex_u16_values() {
+        assert_eq!(parse_hex_u16("1d6b"), Ok(0x1d6b));
+        assert_eq!(parse_hex_u16("0x1d6b"), Ok(0x1d6b));
+        assert_eq!(parse_hex_u16("0X1D6B"), Ok(0x1d6b));
+        assert_eq!(parse_hex_u16("ffff"), Ok(0xffff));
+
+        assert!(parse_hex_u16("").is_err());
+        assert!(parse_hex_u16("0x").is_err());
+        assert!(parse_hex_u16("10000").is_err());
+        assert!(parse_hex_u16("0x0x1d6b").is_err());
+        assert!(parse_hex_u16("usb").is_err());
+    }
+
+    #[test]
+    fn bool_values() {
+        assert_eq!(parse_bool("1"), Ok(true));
+        assert_eq!(parse_bool("Y"), Ok(true));
+        assert_eq!(parse_bool("y"), Ok(true));
+        assert_eq!(parse_bool("0"), Ok(false));
+        assert_eq!(parse_bool("N"), Ok(false));
+        assert_eq!(parse_bool("n"), Ok(false));
+
+        assert!(parse_bool("").is_err());
+        assert!(parse_bool("yes").is_err());
+        assert!(parse_bool("2").is_err());
+    }
+
+    #[test]
+    fn values_are_trimmed_before_parsing() {
+        assert_eq!(parse_os_str(OsStr::new(" 0x1d6b\n"), parse_hex_u16), Ok(0x1d6b));
+        assert_eq!(parse_os_str(OsStr::new("\tY \n"), parse_bool), Ok(true));
+        assert!(parse_os_str(OsStr::from_bytes(b"\xff"), parse_bool).is_err());
+    }
+
+    #[test]
+    fn attribute_as() {
+        let context = Context::new().unwrap();
+        let device = loopback(&context);
+
+        assert_eq!(device.attribute_as::<u32>("ifindex").unwrap(), Some(1));
+        assert_eq!(device.attribute_hex_u16("addr_len").unwrap(), Some(6));
+        assert_eq!(device.attribute_as::<u32>("libudev-rs-test").unwrap(), None);
+
+        assert_eq!(device.attribute_as::<u32>("address").unwrap_err().kind(), ::ErrorKind::Parse);
+        assert_eq!(device.attribute_bool("address").unwrap_err().kind(), ::ErrorKind::Parse);
+    }
+
+    #[test]
+    fn read_attribute_bytes_up_to_limit() {
+        let context = Context::new().unwrap();
+        let device = loopback(&context);
+        let contents = fs::read("/sys/devices/virtual/net/lo/address").unwrap();
+
+        assert_eq!(device.read_attribute_bytes("address", contents.len()).unwrap(), contents);
+
+        let err = device.read_attribute_bytes("address", contents.len() - 1).unwrap_err();
+        assert_eq!(::error::raw_os_error(&err), ::libc::EFBIG);
+    }
+
+    #[test]
+    fn attribute_reader_up_to_limit() {
+        let context = Context::new().unwrap();
+        let device = loopback(&context);
+        let contents = fs::read("/sys/devices/virtual/net/lo/address").unwrap();
+
+        let mut bytes = Vec::new();
+        let mut reader = device.attribute_reader("address", contents.len()).unwrap();
+        assert_eq!(reader.read_to_end(&mut bytes).unwrap(), contents.len());
+        assert_eq!(bytes, contents);
+
+        let mut bytes = Vec::new();
+        let mut reader = device.attribute_reader("address", contents.len() - 1).unwrap();
+        assert_eq!(reader.read_to_end(&mut bytes).unwrap_err().raw_os_error(), Some(::libc::EFBIG));
+        assert_eq!(bytes, &contents[..contents.len() - 1]);
+    }
+}
@@ -27,6 +27,13 @@ impl Drop for Enumerator {
     }
 }
 
+#[doc(hidden)]
+impl Handle<::ffi::udev_enumerate> for Enumerator {
+    fn as_ptr(&self) -> *mut ::ffi::udev_enumerate {
+        self.enumerator
+    }
+}
+
 impl Enumerator {
     /// Creates a new Enumerator.
     pub fn new(context: &Context) -> ::Result<Self> {
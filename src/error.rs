@@ -12,10 +12,15 @@ use libc::c_int;
 pub type Result<T> = StdResult<T,Error>;
 
 /// Types of errors that occur in libudev.
+///
+/// More variants may be added in the future, so matches on `ErrorKind` need a wildcard arm.
 #[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[non_exhaustive]
 pub enum ErrorKind {
     NoMem,
     InvalidInput,
+    Overflow,
+    Parse,
     Io(io::ErrorKind),
 }
 
@@ -23,6 +28,8 @@ pub enum ErrorKind {
 #[derive(Debug)]
 pub struct Error {
     errno: c_int,
+    kind: Option<ErrorKind>,
+    message: Option<String>,
 }
 
 impl Error {
@@ -34,28 +41,36 @@ impl Error {
 
     /// Returns the corresponding `ErrorKind` for this error.
     pub fn kind(&self) -> ErrorKind {
+        if let Some(kind) = self.kind {
+            return kind;
+        }
+
         match self.errno {
             ::libc::ENOMEM => ErrorKind::NoMem,
             ::libc::EINVAL => ErrorKind::InvalidInput,
+            ::libc::ENOBUFS => ErrorKind::Overflow,
             errno => ErrorKind::Io(io::Error::from_raw_os_error(errno).kind()),
         }
     }
 
     /// Returns a description of the error.
     pub fn description(&self) -> &str {
-        self.strerror()
+        match self.message {
+            Some(ref message) => message,
+            None => self.strerror(),
+        }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> StdResult<(),fmt::Error> {
-        fmt.write_str(self.strerror())
+        fmt.write_str(Error::description(self))
     }
 }
 
 impl StdError for Error {
     fn description(&self) -> &str {
-        self.strerror()
+        Error::description(self)
     }
 }
 
@@ -65,12 +80,42 @@ impl From<Error> for io::Error {
             ErrorKind::Io(kind) => kind,
             ErrorKind::InvalidInput => io::ErrorKind::InvalidInput,
             ErrorKind::NoMem => io::ErrorKind::Other,
+            ErrorKind::Overflow => io::ErrorKind::Other,
+            ErrorKind::Parse => io::ErrorKind::InvalidData,
         };
 
-        io::Error::new(io_error_kind, error.strerror())
+        io::Error::new(io_error_kind, Error::description(&error))
     }
 }
 
+/// Returns the positive `errno` value of an error.
+pub fn raw_os_error(error: &Error) -> c_int {
+    error.errno
+}
+
 pub fn from_errno(errno: c_int) -> Error {
-    Error { errno: -errno }
+    Error { errno: -errno, kind: None, message: None }
+}
+
+pub fn from_raw_os_error(errno: c_int) -> Error {
+    Error { errno: errno, kind: None, message: None }
+}
+
+/// Creates an error for a positive `errno` value with a message that replaces the description of
+/// `errno`.
+pub fn with_message(errno: c_int, message: String) -> Error {
+    Error { errno: errno, kind: None, message: Some(message) }
+}
+
+/// Creates an error for a value that could not be parsed.
+pub fn parse_error(message: String) -> Error {
+    Error { errno: ::libc::EINVAL, kind: Some(ErrorKind::Parse), message: Some(message) }
+}
+
+pub fn last_os_error() -> Error {
+    from_io_error(io::Error::last_os_error())
+}
+
+pub fn from_io_error(error: io::Error) -> Error {
+    Error { errno: error.raw_os_error().unwrap_or(::libc::EIO), kind: None, message: None }
 }
@@ -0,0 +1,56 @@
+use std::mem;
+
+use libc::{c_char, c_int, c_uint, c_void};
+
+pub use libudev_sys::*;
+
+
+// `udev_device_get_current_tags_list_entry()` and `udev_device_has_current_tag()` were added to
+// libudev in systemd 247 and aren't bound by `libudev-sys`. They are looked up at runtime, so that
+// older versions of libudev keep working. With older versions, all tags are treated as current.
+
+pub unsafe fn udev_device_get_current_tags_list_entry(udev_device: *mut udev_device) -> *mut udev_list_entry {
+    match lookup(b"udev_device_get_current_tags_list_entry\0") {
+        Some(symbol) => {
+            let function: unsafe extern "C" fn(*mut udev_device) -> *mut udev_list_entry = mem::transmute(symbol);
+            function(udev_device)
+        },
+        None => udev_device_get_tags_list_entry(udev_device),
+    }
+}
+
+pub unsafe fn udev_device_has_current_tag(udev_device: *mut udev_device, tag: *const c_char) -> c_int {
+    match lookup(b"udev_device_has_current_tag\0") {
+        Some(symbol) => {
+            let function: unsafe extern "C" fn(*mut udev_device, *const c_char) -> c_int = mem::transmute(symbol);
+            function(udev_device, tag)
+        },
+        None => udev_device_has_tag(udev_device, tag),
+    }
+}
+
+// The hwdb functions are only bound by `libudev-sys` if its build script manages to link a test
+// program against libudev, which fails when libudev isn't in the linker's default search path. They
+// have been part of libudev since systemd 196, so they are declared here.
+
+#[repr(C)]
+pub struct udev_hwdb {
+    __private: c_void,
+}
+
+extern "C" {
+    pub fn udev_hwdb_unref(hwdb: *mut udev_hwdb) -> *mut udev_hwdb;
+    pub fn udev_hwdb_new(udev: *mut udev) -> *mut udev_hwdb;
+    pub fn udev_hwdb_get_properties_list_entry(hwdb: *mut udev_hwdb, modalias: *const c_char, flags: c_uint) -> *mut udev_list_entry;
+}
+
+unsafe fn lookup(name: &[u8]) -> Option<*mut c_void> {
+    let symbol = ::libc::dlsym(::libc::RTLD_DEFAULT, name.as_ptr() as *const c_char);
+
+    if !symbol.is_null() {
+        Some(symbol)
+    }
+    else {
+        None
+    }
+}
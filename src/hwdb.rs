@@ -0,0 +1,81 @@
+use std::ffi::OsStr;
+use std::ptr;
+
+use ::context::Context;
+use ::device::{self, Device, Properties};
+use ::handle::Handle;
+
+
+/// The hardware database.
+///
+/// The hardware database maps modalias strings, such as `usb:v046DpC52B`, to properties that
+/// describe the hardware, like its vendor and model names. It is compiled by `systemd-hwdb` from
+/// the files in `hwdb.d` directories.
+///
+/// ## Example
+///
+/// ```no_run
+/// let context = libudev::Context::new().unwrap();
+/// let mut hwdb = libudev::Hwdb::new(&context).unwrap();
+///
+/// for property in hwdb.query("usb:v046DpC52B").unwrap() {
+///     println!("{:?} = {:?}", property.name(), property.value());
+/// }
+/// ```
+pub struct Hwdb {
+    hwdb: *mut ::ffi::udev_hwdb,
+    _context: Context,
+}
+
+impl Drop for Hwdb {
+    fn drop(&mut self) {
+        unsafe {
+            ::ffi::udev_hwdb_unref(self.hwdb);
+        }
+    }
+}
+
+impl Hwdb {
+    /// Opens the hardware database.
+    ///
+    /// Returns an error if the compiled database can't be found or read.
+    pub fn new(context: &Context) -> ::Result<Self> {
+        let hwdb = unsafe {
+            ::ffi::udev_hwdb_new(context.as_ptr())
+        };
+
+        if hwdb.is_null() {
+            return Err(::error::last_os_error());
+        }
+
+        Ok(Hwdb {
+            hwdb: hwdb,
+            _context: context.clone(),
+        })
+    }
+
+    /// Looks up the properties of a modalias.
+    ///
+    /// The properties of all entries whose patterns match the modalias are combined and sorted by
+    /// name. The iterator borrows the database, because the next query replaces its results.
+    pub fn query<T: AsRef<OsStr>>(&mut self, modalias: T) -> ::Result<Properties<'_>> {
+        let modalias = try!(::util::os_str_to_cstring(modalias));
+
+        Ok(unsafe {
+            device::properties_from_raw(::ffi::udev_hwdb_get_properties_list_entry(self.hwdb, modalias.as_ptr(), 0))
+        })
+    }
+}
+
+impl Device {
+    /// Looks up the device's properties in the hardware database.
+    ///
+    /// The device's `MODALIAS` property is used as the lookup key. The iterator is empty if the
+    /// device doesn't have a modalias.
+    pub fn hwdb_properties<'a>(&self, hwdb: &'a mut Hwdb) -> ::Result<Properties<'a>> {
+        match self.property_value("MODALIAS") {
+            Some(modalias) => hwdb.query(modalias),
+            None => Ok(unsafe { device::properties_from_raw(ptr::null_mut()) }),
+        }
+    }
+}
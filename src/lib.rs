@@ -1,11 +1,39 @@
-extern crate libudev_sys as ffi;
+#[cfg(not(feature = "sysfs"))]
+extern crate libudev_sys;
 extern crate libc;
 
+#[cfg(feature = "crossbeam-channel")]
+extern crate crossbeam_channel;
+#[cfg(feature = "mio")]
+extern crate mio;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
 pub use context::Context;
-pub use device::{Device, Properties, Property, Attributes, Attribute};
+pub use device::{Device, DeviceType, Ancestors, Children, Descendants, Properties, Property, Attributes, Attribute, AttributeReader, Tags, Devlinks};
 pub use enumerator::{Enumerator, Devices};
+pub use db::{Database, DeviceRecord, Records};
+pub use hwdb::Hwdb;
+pub use queue::Queue;
+pub use trigger::Trigger;
 pub use error::{Result, Error, ErrorKind};
-pub use monitor::{Monitor, MonitorSocket, EventType, Event};
+pub use snapshot::{DeviceSnapshot, ParentSnapshot, EventSnapshot};
+pub use monitor_thread::{MonitorThread, MonitorThreadBuilder, EventSender};
+pub use monitor::{Monitor, MonitorSocket, Drain, Iter, WakeHandle, EventType, EventSource, Event};
+
+#[cfg(feature = "tokio")]
+pub use settle::Settle;
+#[cfg(feature = "tokio")]
+pub use stream::MonitorStream;
+
+#[cfg(not(any(feature = "libudev-sys", feature = "sysfs")))]
+compile_error!("either the `libudev-sys` feature or the `sysfs` feature must be enabled");
 
 macro_rules! try_alloc {
     ($exp:expr) => {{
@@ -20,10 +48,30 @@ macro_rules! try_alloc {
 }
 
 mod context;
+mod db;
 mod device;
 mod enumerator;
 mod error;
+mod hwdb;
 mod monitor;
+mod monitor_thread;
+mod queue;
+mod snapshot;
+mod trigger;
+
+#[cfg(feature = "mio")]
+mod source;
+#[cfg(feature = "tokio")]
+mod settle;
+#[cfg(feature = "tokio")]
+mod stream;
+
+#[cfg(not(feature = "sysfs"))]
+mod ffi;
+#[cfg(feature = "sysfs")]
+mod sysfs;
+#[cfg(feature = "sysfs")]
+use sysfs as ffi;
 
 mod handle;
 mod util;
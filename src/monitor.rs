@@ -1,9 +1,15 @@
 use std::fmt;
+use std::io;
+use std::mem;
 use std::ptr;
 
 use std::ffi::OsStr;
 use std::ops::Deref;
 use std::os::unix::io::{RawFd, AsRawFd};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use libc::c_int;
 
 use ::context::Context;
 use ::device::Device;
@@ -13,10 +19,12 @@ use ::handle::Handle;
 /// Monitors for device events.
 ///
 /// A monitor communicates with the kernel over a socket. Filtering events is performed efficiently
-/// in the kernel, and only events that match the filters are received by the socket. Filters must
-/// be setup before listening for events.
+/// in the kernel, and only events that match the filters are received by the socket. Filters are
+/// usually setup before listening for events, but they can also be changed later on the
+/// `MonitorSocket`.
 pub struct Monitor {
     monitor: *mut ::ffi::udev_monitor,
+    source: EventSource,
 }
 
 impl Drop for Monitor {
@@ -31,19 +39,39 @@ impl Drop for Monitor {
 }
 
 impl Monitor {
-    /// Creates a new `Monitor`.
+    /// Creates a new `Monitor` that listens to events from udev.
     pub fn new(context: &Context) -> ::Result<Self> {
+        Monitor::with_source(context, EventSource::Udev)
+    }
+
+    /// Creates a new `Monitor` that listens to events from the given source.
+    ///
+    /// Monitoring events from the kernel doesn't require the udev daemon to be running. This can be
+    /// useful in early boot environments or to observe uevents before udev's rules are applied. Most
+    /// applications should monitor events from udev instead, because a device is only ready to be
+    /// used after udev has finished processing it.
+    pub fn with_source(context: &Context, source: EventSource) -> ::Result<Self> {
+        let name: &[u8] = match source {
+            EventSource::Udev => b"udev\0",
+            EventSource::Kernel => b"kernel\0",
+        };
+
         unsafe {
             let ptr = try_alloc!(
-                ::ffi::udev_monitor_new_from_netlink(context.as_ptr(), b"udev\0".as_ptr() as *mut _)
+                ::ffi::udev_monitor_new_from_netlink(context.as_ptr(), name.as_ptr() as *mut _)
             );
 
             ::ffi::udev_ref(context.as_ptr());
 
-            Ok(Monitor { monitor: ptr })
+            Ok(Monitor { monitor: ptr, source: source })
         }
     }
 
+    /// Returns the source of the events that the monitor listens to.
+    pub fn source(&self) -> EventSource {
+        self.source
+    }
+
     /// Adds a filter that matches events for devices with the given subsystem.
     pub fn match_subsystem<T: AsRef<OsStr>>(&mut self, subsystem: T) -> ::Result<()> {
         let subsystem = try!(::util::os_str_to_cstring(subsystem));
@@ -79,6 +107,22 @@ impl Monitor {
         })
     }
 
+    /// Sets the size of the socket's receive buffer in bytes.
+    ///
+    /// Events that arrive while the receive buffer is full are dropped by the kernel. Increasing the
+    /// buffer size reduces the chance of losing events during bursts of activity, such as when a USB
+    /// hub with many devices is attached. Setting the size may require the `CAP_NET_ADMIN`
+    /// capability.
+    pub fn set_receive_buffer_size(&mut self, size: usize) -> ::Result<()> {
+        if size > c_int::MAX as usize {
+            return Err(::error::from_raw_os_error(::libc::EINVAL));
+        }
+
+        ::util::errno_to_result(unsafe {
+            ::ffi::udev_monitor_set_receive_buffer_size(self.monitor, size as c_int)
+        })
+    }
+
     /// Listens for events matching the current filters.
     ///
     /// This method consumes the `Monitor`.
@@ -87,7 +131,7 @@ impl Monitor {
             ::ffi::udev_monitor_enable_receiving(self.monitor)
         }));
 
-        Ok(MonitorSocket { inner: self })
+        Ok(MonitorSocket { inner: self, wake_handle: None, overflowed: false })
     }
 }
 
@@ -99,9 +143,15 @@ impl Monitor {
 ///
 /// Monitors are initially setup to receive events from the kernel via a nonblocking socket. A
 /// variant of `poll()` should be used on the file descriptor returned by the `AsRawFd` trait to
-/// wait for new events.
+/// wait for new events. Alternatively, `recv_timeout()` and `iter()` block until an event is
+/// received.
+///
+/// If events arrive faster than they are received, the socket's receive buffer can overflow and
+/// events are lost. Overflows are reported by `take_overflow()`.
 pub struct MonitorSocket {
     inner: Monitor,
+    wake_handle: Option<WakeHandle>,
+    overflowed: bool,
 }
 
 /// Provides raw access to the monitor's socket.
@@ -119,23 +169,371 @@ impl MonitorSocket {
     ///
     /// This method does not block. If no events are available, it returns `None` immediately.
     pub fn receive_event(&mut self) -> Option<Event> {
+        loop {
+            match self.try_receive_event() {
+                Ok(event) => return event,
+                Err(ref err) if err.kind() == ::ErrorKind::Overflow => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Receives the next available event from the monitor, reporting errors.
+    ///
+    /// This method does not block. Unlike `receive_event()`, it distinguishes between the absence of
+    /// events, which returns `Ok(None)`, and a failure to receive an event, which returns an error.
+    ///
+    /// If events were lost because the socket's receive buffer overflowed, this method returns an
+    /// error of kind `ErrorKind::Overflow`. Events that are still queued can be received by calling
+    /// this method again.
+    pub fn try_receive_event(&mut self) -> ::Result<Option<Event>> {
+        unsafe {
+            *::libc::__errno_location() = 0;
+        }
+
         let device = unsafe {
             ::ffi::udev_monitor_receive_device(self.inner.monitor)
         };
 
         if !device.is_null() {
-            Some(Event {
+            return Ok(Some(Event {
                 device: unsafe { ::device::from_raw(device) },
-            })
+                source: self.inner.source,
+            }));
+        }
+
+        match io::Error::last_os_error().raw_os_error() {
+            Some(0) | None => Ok(None),
+            Some(errno) if errno == ::libc::EAGAIN || errno == ::libc::EWOULDBLOCK => Ok(None),
+            Some(errno) => {
+                if errno == ::libc::ENOBUFS {
+                    self.overflowed = true;
+                }
+
+                Err(::error::from_raw_os_error(errno))
+            },
+        }
+    }
+
+    /// Returns whether events have been lost because the socket's receive buffer overflowed.
+    ///
+    /// The overflow state is cleared by calling this method. After an overflow, the events that were
+    /// received can no longer be relied upon to describe the current state of the system.
+    /// Applications that track devices should resynchronize their state by scanning the devices
+    /// with an `Enumerator`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # let context = libudev::Context::new().unwrap();
+    /// # let mut socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+    /// for event in socket.drain().filter_map(Result::ok) {
+    ///     println!("{}: {:?}", event.event_type(), event.syspath());
+    /// }
+    ///
+    /// if socket.take_overflow() {
+    ///     let mut enumerator = libudev::Enumerator::new(&context).unwrap();
+    ///
+    ///     for device in enumerator.scan_devices().unwrap() {
+    ///         println!("present: {:?}", device.syspath());
+    ///     }
+    /// }
+    /// ```
+    pub fn take_overflow(&mut self) -> bool {
+        let overflowed = self.overflowed;
+        self.overflowed = false;
+
+        overflowed
+    }
+
+    /// Adds a filter that matches events for devices with the given subsystem.
+    ///
+    /// The filter takes effect when `update_filters()` is called.
+    pub fn match_subsystem<T: AsRef<OsStr>>(&mut self, subsystem: T) -> ::Result<()> {
+        self.inner.match_subsystem(subsystem)
+    }
+
+    /// Adds a filter that matches events for devices with the given subsystem and device type.
+    ///
+    /// The filter takes effect when `update_filters()` is called.
+    pub fn match_subsystem_devtype<T: AsRef<OsStr>, U: AsRef<OsStr>>(&mut self, subsystem: T, devtype: U) -> ::Result<()> {
+        self.inner.match_subsystem_devtype(subsystem, devtype)
+    }
+
+    /// Adds a filter that matches events for devices with the given tag.
+    ///
+    /// The filter takes effect when `update_filters()` is called.
+    pub fn match_tag<T: AsRef<OsStr>>(&mut self, tag: T) -> ::Result<()> {
+        self.inner.match_tag(tag)
+    }
+
+    /// Removes all filters currently set on the socket.
+    ///
+    /// Without any filters, the socket receives all events. Filters that are added afterwards take
+    /// effect when `update_filters()` is called.
+    pub fn clear_filters(&mut self) -> ::Result<()> {
+        self.inner.clear_filters()
+    }
+
+    /// Applies the current filters to the socket.
+    ///
+    /// The socket keeps receiving events while its filters are updated, so no events are missed
+    /// between closing and reopening a socket. Events that were already queued on the socket before
+    /// the update are still received, even if they don't match the new filters.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # let context = libudev::Context::new().unwrap();
+    /// # let mut socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+    /// socket.clear_filters().unwrap();
+    /// socket.match_subsystem("block").unwrap();
+    /// socket.match_subsystem_devtype("usb", "usb_device").unwrap();
+    /// socket.update_filters().unwrap();
+    /// ```
+    pub fn update_filters(&mut self) -> ::Result<()> {
+        ::util::errno_to_result(unsafe {
+            ::ffi::udev_monitor_filter_update(self.inner.monitor)
+        })
+    }
+
+    /// Sets the size of the socket's receive buffer in bytes.
+    ///
+    /// See `Monitor::set_receive_buffer_size()`.
+    pub fn set_receive_buffer_size(&mut self, size: usize) -> ::Result<()> {
+        self.inner.set_receive_buffer_size(size)
+    }
+
+    /// Blocks until an event is received or the timeout expires.
+    ///
+    /// If no event is received before the timeout expires, this method returns an error of kind
+    /// `ErrorKind::Io(io::ErrorKind::TimedOut)`. If the socket is woken up through a `WakeHandle`,
+    /// it returns an error of kind `ErrorKind::Io(io::ErrorKind::Interrupted)`.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> ::Result<Event> {
+        match try!(self.wait_for_event(Some(timeout))) {
+            Some(event) => Ok(event),
+            None => Err(::error::from_raw_os_error(::libc::EINTR)),
         }
-        else {
-            None
+    }
+
+    /// Returns an iterator that blocks until each event is received.
+    ///
+    /// The iterator yields an error if receiving an event fails, after which it can continue to be
+    /// used. It ends when the socket is woken up through a `WakeHandle`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # let context = libudev::Context::new().unwrap();
+    /// # let mut socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+    /// for event in socket.iter() {
+    ///     let event = event.unwrap();
+    ///     println!("{}: {:?}", event.event_type(), event.syspath());
+    /// }
+    /// ```
+    pub fn iter(&mut self) -> Iter<'_> {
+        Iter { socket: self }
+    }
+
+    /// Returns a handle that can wake up the socket from another thread.
+    ///
+    /// Waking up the socket interrupts a blocking call to `recv_timeout()` or `iter()`. If the socket
+    /// is not blocked when it is woken up, the next blocking call returns immediately. All handles
+    /// returned by this method wake up the same socket.
+    pub fn wake_handle(&mut self) -> ::Result<WakeHandle> {
+        if let Some(ref handle) = self.wake_handle {
+            return Ok(handle.clone());
+        }
+
+        let handle = try!(WakeHandle::new());
+        self.wake_handle = Some(handle.clone());
+
+        Ok(handle)
+    }
+
+    /// Blocks until an event is received, returning `None` if the socket is woken up.
+    fn wait_for_event(&mut self, timeout: Option<Duration>) -> ::Result<Option<Event>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut fds = vec!(self.as_raw_fd());
+
+        if let Some(ref handle) = self.wake_handle {
+            fds.push(handle.event_fd.fd);
+        }
+
+        loop {
+            // The socket is polled at least once, even if the deadline has already passed, so that
+            // queued events are received with a zero timeout.
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+            let ready = match ::util::poll_readable(&fds, remaining) {
+                Ok(ready) => ready,
+                Err(err) => {
+                    // An overflow is reported as a pending error of the socket.
+                    if err.kind() == ::ErrorKind::Overflow {
+                        self.overflowed = true;
+                    }
+
+                    return Err(err);
+                },
+            };
+
+            if ready.len() > 1 && ready[1] {
+                if let Some(ref handle) = self.wake_handle {
+                    handle.event_fd.reset();
+                }
+
+                return Ok(None);
+            }
+
+            if ready[0] {
+                if let Some(event) = try!(self.try_receive_event()) {
+                    return Ok(Some(event));
+                }
+            }
+
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return Err(::error::from_raw_os_error(::libc::ETIMEDOUT));
+                }
+            }
         }
     }
+
+    /// Returns an iterator that receives all events that are currently available.
+    ///
+    /// The iterator ends as soon as no more events are available, without blocking. Edge-triggered
+    /// readiness notifications, such as those delivered by `epoll` with `EPOLLET` or by `mio`, are
+    /// only raised again after the socket has been drained, so every notification should be
+    /// followed by draining the socket completely.
+    ///
+    /// The iterator yields an error if receiving an event fails, for example with kind
+    /// `ErrorKind::Overflow` when events were lost, and keeps receiving the remaining events
+    /// afterwards. It only ends once the socket has no more events available.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # let context = libudev::Context::new().unwrap();
+    /// # let mut socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+    /// for event in socket.drain() {
+    ///     match event {
+    ///         Ok(event) => println!("{}: {:?}", event.event_type(), event.syspath()),
+    ///         Err(err) => println!("error: {}", err),
+    ///     }
+    /// }
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_> {
+        Drain { socket: self }
+    }
+}
+
+
+/// Iterator over the events that are available on a `MonitorSocket`.
+pub struct Drain<'a> {
+    socket: &'a mut MonitorSocket,
 }
 
+impl<'a> Iterator for Drain<'a> {
+    type Item = ::Result<Event>;
+
+    fn next(&mut self) -> Option<::Result<Event>> {
+        match self.socket.try_receive_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+/// Iterator that blocks until each event is received by a `MonitorSocket`.
+pub struct Iter<'a> {
+    socket: &'a mut MonitorSocket,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = ::Result<Event>;
+
+    fn next(&mut self) -> Option<::Result<Event>> {
+        match self.socket.wait_for_event(None) {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+
+/// A handle that wakes up a `MonitorSocket` that is blocked waiting for events.
+///
+/// Unlike the socket itself, a `WakeHandle` can be sent to and shared with other threads.
+#[derive(Clone)]
+pub struct WakeHandle {
+    event_fd: Arc<EventFd>,
+}
+
+impl WakeHandle {
+    fn new() -> ::Result<Self> {
+        let fd = unsafe { ::libc::eventfd(0, ::libc::EFD_CLOEXEC | ::libc::EFD_NONBLOCK) };
+
+        if fd < 0 {
+            return Err(::error::last_os_error());
+        }
+
+        Ok(WakeHandle { event_fd: Arc::new(EventFd { fd: fd }) })
+    }
+
+    /// Wakes up the socket.
+    pub fn wake(&self) -> ::Result<()> {
+        let value: u64 = 1;
+
+        let result = unsafe {
+            ::libc::write(self.event_fd.fd, &value as *const u64 as *const _, mem::size_of::<u64>())
+        };
+
+        // EAGAIN means the counter is saturated, so the socket is already awake.
+        if result < 0 && io::Error::last_os_error().raw_os_error() != Some(::libc::EAGAIN) {
+            return Err(::error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+struct EventFd {
+    fd: RawFd,
+}
+
+impl EventFd {
+    fn reset(&self) {
+        let mut value: u64 = 0;
+
+        unsafe {
+            ::libc::read(self.fd, &mut value as *mut u64 as *mut _, mem::size_of::<u64>());
+        }
+    }
+}
+
+impl Drop for EventFd {
+    fn drop(&mut self) {
+        unsafe {
+            ::libc::close(self.fd);
+        }
+    }
+}
+
+
 /// Types of events that can be received from udev.
-#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+///
+/// More variants may be added as the kernel gains new actions, so matches on `EventType` need a
+/// wildcard arm. Until a variant is added, its action is reported as `EventType::Other`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
 pub enum EventType {
     /// A device was added.
     Add,
@@ -146,7 +544,27 @@ pub enum EventType {
     /// A device was removed.
     Remove,
 
-    /// An unknown event occurred.
+    /// A driver was bound to a device.
+    Bind,
+
+    /// A driver was unbound from a device.
+    Unbind,
+
+    /// A device was renamed or moved to a new parent. The previous devpath is available from
+    /// `Event::devpath_old()`.
+    Move,
+
+    /// A device was brought online, e.g., a CPU or memory block.
+    Online,
+
+    /// A device was taken offline.
+    Offline,
+
+    /// An event with an action that is not covered by the other variants. The value is the raw
+    /// action string.
+    Other(String),
+
+    /// An event without an action.
     Unknown,
 }
 
@@ -162,15 +580,58 @@ impl fmt::Display for EventType {
             &EventType::Add => "add",
             &EventType::Change => "change",
             &EventType::Remove => "remove",
+            &EventType::Bind => "bind",
+            &EventType::Unbind => "unbind",
+            &EventType::Move => "move",
+            &EventType::Online => "online",
+            &EventType::Offline => "offline",
+            &EventType::Other(ref action) => action,
             &EventType::Unknown => "unknown",
         })
     }
 }
 
+/// Returns the `EventType` corresponding to a raw action string.
+pub fn event_type_from_action(action: &OsStr) -> EventType {
+    match action.to_str() {
+        Some("add") => EventType::Add,
+        Some("change") => EventType::Change,
+        Some("remove") => EventType::Remove,
+        Some("bind") => EventType::Bind,
+        Some("unbind") => EventType::Unbind,
+        Some("move") => EventType::Move,
+        Some("online") => EventType::Online,
+        Some("offline") => EventType::Offline,
+        _ => EventType::Other(action.to_string_lossy().into_owned()),
+    }
+}
+
+
+/// Sources of events that can be monitored.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum EventSource {
+    /// Events that are sent by udev after it has processed its rules.
+    Udev,
+
+    /// Uevents that are sent by the kernel before udev has processed them.
+    Kernel,
+}
+
+impl fmt::Display for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            &EventSource::Udev => "udev",
+            &EventSource::Kernel => "kernel",
+        })
+    }
+}
+
 
 /// An event that indicates a change in device state.
 pub struct Event {
     device: Device,
+    source: EventSource,
 }
 
 /// Provides access to the device associated with the event.
@@ -185,19 +646,24 @@ impl Deref for Event {
 impl Event {
     /// Returns the `EventType` corresponding to this event.
     pub fn event_type(&self) -> EventType {
-        let value = match self.device.property_value("ACTION") {
-            Some(s) => s.to_str(),
-            None => None,
-        };
-
-        match value {
-            Some("add") => EventType::Add,
-            Some("change") => EventType::Change,
-            Some("remove") => EventType::Remove,
-            _ => EventType::Unknown
+        match self.device.action() {
+            Some(action) => event_type_from_action(action),
+            None => EventType::Unknown,
         }
     }
 
+    /// Returns the devpath the device had before it was moved.
+    ///
+    /// This is only set for events of type `EventType::Move`.
+    pub fn devpath_old(&self) -> Option<&OsStr> {
+        self.device.property_value("DEVPATH_OLD")
+    }
+
+    /// Returns the source of this event.
+    pub fn source(&self) -> EventSource {
+        self.source
+    }
+
     /// Returns the event's sequence number.
     pub fn sequence_number(&self) -> u64 {
         unsafe {
@@ -210,3 +676,80 @@ impl Event {
         &self.device
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use ::context::Context;
+
+    use super::{EventSource, Monitor, MonitorSocket};
+
+    /// Returns a socket whose filter doesn't match any events.
+    fn quiet_socket() -> MonitorSocket {
+        let context = Context::new().unwrap();
+        let mut monitor = Monitor::with_source(&context, EventSource::Kernel).unwrap();
+        monitor.match_subsystem("libudev-rs-test").unwrap();
+
+        monitor.listen().unwrap()
+    }
+
+    #[test]
+    fn recv_timeout_times_out() {
+        let mut socket = quiet_socket();
+
+        for &timeout in &[Duration::from_secs(0), Duration::from_millis(50)] {
+            let start = Instant::now();
+            let err = socket.recv_timeout(timeout).err().unwrap();
+
+            assert_eq!(err.kind(), ::ErrorKind::Io(io::ErrorKind::TimedOut));
+            assert!(start.elapsed() >= timeout);
+        }
+    }
+
+    #[test]
+    fn wake_handle_interrupts_recv_timeout() {
+        let mut socket = quiet_socket();
+        let handle = socket.wake_handle().unwrap();
+
+        let waker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.wake().unwrap();
+        });
+
+        let err = socket.recv_timeout(Duration::from_secs(30)).err().unwrap();
+        assert_eq!(err.kind(), ::ErrorKind::Io(io::ErrorKind::Interrupted));
+
+        waker.join().unwrap();
+    }
+
+    #[test]
+    fn wake_handle_ends_iter() {
+        let mut socket = quiet_socket();
+        let handle = socket.wake_handle().unwrap();
+
+        let waker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.wake().unwrap();
+        });
+
+        assert!(socket.iter().next().is_none());
+
+        waker.join().unwrap();
+    }
+
+    #[test]
+    fn wake_before_blocking_returns_immediately() {
+        let mut socket = quiet_socket();
+
+        socket.wake_handle().unwrap().wake().unwrap();
+        assert!(socket.iter().next().is_none());
+
+        // The wake-up is consumed, so the next call waits again.
+        let err = socket.recv_timeout(Duration::from_secs(0)).err().unwrap();
+        assert_eq!(err.kind(), ::ErrorKind::Io(io::ErrorKind::TimedOut));
+    }
+}
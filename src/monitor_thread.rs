@@ -0,0 +1,352 @@
+use std::ffi::{OsStr, OsString};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use ::context::Context;
+use ::monitor::{EventSource, Monitor, WakeHandle};
+use ::snapshot::EventSnapshot;
+
+
+/// A monitor that receives events on a background thread.
+///
+/// Because a `MonitorSocket` can't be sent between threads, a `MonitorThread` creates its own
+/// `Context` and `MonitorSocket` on a background thread. Received events are delivered as
+/// `EventSnapshot`s to an `EventSender`, such as the sending half of a `std::sync::mpsc` channel.
+///
+/// The background thread stops when the `MonitorThread` is dropped or stopped, even while it's
+/// waiting for room in a full bounded channel. It also stops when the receiving half of the channel
+/// is disconnected, but that's only noticed when the next event is delivered, so the thread keeps
+/// waiting for events until then.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc;
+/// let (sender, receiver) = mpsc::channel();
+///
+/// let monitor = libudev::MonitorThread::builder()
+///     .match_subsystem_devtype("usb", "usb_device")
+///     .spawn(sender)
+///     .unwrap();
+///
+/// for event in receiver {
+///     println!("{}: {}", event.event_type(), event.syspath().display());
+/// }
+/// ```
+pub struct MonitorThread {
+    handle: Option<thread::JoinHandle<::Result<()>>>,
+    wake_handle: WakeHandle,
+    overflowed: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Drop for MonitorThread {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stopped.store(true, Ordering::SeqCst);
+            let _ = self.wake_handle.wake();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl MonitorThread {
+    /// Creates a builder for configuring a `MonitorThread`.
+    pub fn builder() -> MonitorThreadBuilder {
+        MonitorThreadBuilder::new()
+    }
+
+    /// Returns whether events have been lost because the monitor's receive buffer overflowed.
+    ///
+    /// The overflow state is cleared by calling this method. See `MonitorSocket::take_overflow()`.
+    pub fn take_overflow(&self) -> bool {
+        self.overflowed.swap(false, Ordering::SeqCst)
+    }
+
+    /// Stops the background thread and waits for it to finish.
+    ///
+    /// Returns the error that stopped the background thread, if any.
+    pub fn stop(mut self) -> ::Result<()> {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _ = self.wake_handle.wake();
+
+        match self.handle.take() {
+            Some(handle) => match handle.join() {
+                Ok(result) => result,
+                Err(_) => Err(::error::with_message(::libc::EIO, String::from("monitor thread panicked"))),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+
+// How long the background thread waits for room in a full channel before it checks whether it
+// was stopped.
+const SEND_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Destinations that a `MonitorThread` can deliver events to.
+///
+/// This trait is implemented for the sending halves of `std::sync::mpsc` channels and, with the
+/// `crossbeam-channel` feature, of `crossbeam_channel` channels.
+pub trait EventSender: Send + 'static {
+    /// Tries to send an event without blocking.
+    ///
+    /// Returns `TrySendError::Full` if the event can't be sent right now, in which case the
+    /// `MonitorThread` retries it until it's stopped. Returns `TrySendError::Disconnected` if the
+    /// receiving half is disconnected, which stops the `MonitorThread`.
+    // The error hands the event back, like the channels' own `try_send()` methods.
+    #[allow(clippy::result_large_err)]
+    fn try_send_event(&self, event: EventSnapshot) -> Result<(), mpsc::TrySendError<EventSnapshot>>;
+
+    /// Sends an event, waiting up to `timeout` for room in the channel.
+    ///
+    /// Returns `TrySendError::Full` if there's still no room after `timeout`. The default
+    /// implementation calls `try_send_event()` and sleeps for `timeout` if the channel is full.
+    /// Senders that can block with a timeout should override it, so that events are delivered as
+    /// soon as there's room.
+    #[allow(clippy::result_large_err)]
+    fn send_event_timeout(&self, event: EventSnapshot, timeout: Duration) -> Result<(), mpsc::TrySendError<EventSnapshot>> {
+        match self.try_send_event(event) {
+            Err(mpsc::TrySendError::Full(event)) => {
+                thread::sleep(timeout);
+                Err(mpsc::TrySendError::Full(event))
+            },
+            result => result,
+        }
+    }
+}
+
+impl EventSender for mpsc::Sender<EventSnapshot> {
+    fn try_send_event(&self, event: EventSnapshot) -> Result<(), mpsc::TrySendError<EventSnapshot>> {
+        self.send(event).map_err(|err| mpsc::TrySendError::Disconnected(err.0))
+    }
+}
+
+/// While the channel is full, events that arrive in the meantime are queued in the monitor's
+/// receive buffer.
+///
+/// A `SyncSender` can't wait for room with a timeout, so the `MonitorThread` polls a full channel
+/// every 10 milliseconds to stay responsive to being stopped. This delays delivery by up to 10
+/// milliseconds after the receiver makes room. A `crossbeam_channel` sender doesn't have this
+/// delay.
+impl EventSender for mpsc::SyncSender<EventSnapshot> {
+    fn try_send_event(&self, event: EventSnapshot) -> Result<(), mpsc::TrySendError<EventSnapshot>> {
+        self.try_send(event)
+    }
+}
+
+/// While the channel is full, events that arrive in the meantime are queued in the monitor's
+/// receive buffer.
+#[cfg(feature = "crossbeam-channel")]
+impl EventSender for ::crossbeam_channel::Sender<EventSnapshot> {
+    fn try_send_event(&self, event: EventSnapshot) -> Result<(), mpsc::TrySendError<EventSnapshot>> {
+        self.try_send(event).map_err(|err| match err {
+            ::crossbeam_channel::TrySendError::Full(event) => mpsc::TrySendError::Full(event),
+            ::crossbeam_channel::TrySendError::Disconnected(event) => mpsc::TrySendError::Disconnected(event),
+        })
+    }
+
+    fn send_event_timeout(&self, event: EventSnapshot, timeout: Duration) -> Result<(), mpsc::TrySendError<EventSnapshot>> {
+        self.send_timeout(event, timeout).map_err(|err| match err {
+            ::crossbeam_channel::SendTimeoutError::Timeout(event) => mpsc::TrySendError::Full(event),
+            ::crossbeam_channel::SendTimeoutError::Disconnected(event) => mpsc::TrySendError::Disconnected(event),
+        })
+    }
+}
+
+/// Delivers an event to the sender, waiting while the channel is full. Returns `false` if the
+/// receiving half is disconnected or the `MonitorThread` was stopped before the event could be
+/// delivered.
+fn deliver<S: EventSender>(sender: &S, mut event: EventSnapshot, stopped: &AtomicBool) -> bool {
+    loop {
+        match sender.send_event_timeout(event, SEND_TIMEOUT) {
+            Ok(()) => return true,
+            Err(mpsc::TrySendError::Disconnected(_)) => return false,
+            Err(mpsc::TrySendError::Full(full)) => event = full,
+        }
+
+        if stopped.load(Ordering::SeqCst) {
+            return false;
+        }
+    }
+}
+
+
+enum Filter {
+    Subsystem(OsString),
+    SubsystemDevtype(OsString, OsString),
+    Tag(OsString),
+}
+
+/// A builder for configuring a `MonitorThread`.
+///
+/// The filters work the same as the filters of a `Monitor`.
+pub struct MonitorThreadBuilder {
+    source: EventSource,
+    filters: Vec<Filter>,
+    receive_buffer_size: Option<usize>,
+    name: Option<String>,
+}
+
+impl MonitorThreadBuilder {
+    /// Creates a builder for a `MonitorThread` that monitors events from udev without any filters.
+    pub fn new() -> Self {
+        MonitorThreadBuilder {
+            source: EventSource::Udev,
+            filters: Vec::new(),
+            receive_buffer_size: None,
+            name: None,
+        }
+    }
+
+    /// Sets the source of the events to monitor.
+    pub fn source(mut self, source: EventSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Adds a filter that matches events for devices with the given subsystem.
+    pub fn match_subsystem<T: AsRef<OsStr>>(mut self, subsystem: T) -> Self {
+        self.filters.push(Filter::Subsystem(subsystem.as_ref().to_os_string()));
+        self
+    }
+
+    /// Adds a filter that matches events for devices with the given subsystem and device type.
+    pub fn match_subsystem_devtype<T: AsRef<OsStr>, U: AsRef<OsStr>>(mut self, subsystem: T, devtype: U) -> Self {
+        self.filters.push(Filter::SubsystemDevtype(subsystem.as_ref().to_os_string(), devtype.as_ref().to_os_string()));
+        self
+    }
+
+    /// Adds a filter that matches events for devices with the given tag.
+    pub fn match_tag<T: AsRef<OsStr>>(mut self, tag: T) -> Self {
+        self.filters.push(Filter::Tag(tag.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets the size of the monitor's receive buffer in bytes.
+    ///
+    /// See `Monitor::set_receive_buffer_size()`.
+    pub fn receive_buffer_size(mut self, size: usize) -> Self {
+        self.receive_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the name of the background thread.
+    pub fn name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Spawns the background thread, which delivers events to the given sender.
+    ///
+    /// This method returns once the monitor is listening for events. Errors that occur while
+    /// setting up the monitor are returned by this method.
+    pub fn spawn<S: EventSender>(self, sender: S) -> ::Result<MonitorThread> {
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let overflowed = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let mut builder = thread::Builder::new();
+
+        builder = match self.name {
+            Some(ref name) => builder.name(name.clone()),
+            None => builder.name(String::from("udev-monitor")),
+        };
+
+        let thread_overflowed = overflowed.clone();
+        let thread_stopped = stopped.clone();
+
+        let handle = match builder.spawn(move || self.run(sender, ready_sender, thread_overflowed, thread_stopped)) {
+            Ok(handle) => handle,
+            Err(err) => return Err(::error::from_io_error(err)),
+        };
+
+        match ready_receiver.recv() {
+            Ok(Ok(wake_handle)) => {
+                Ok(MonitorThread {
+                    handle: Some(handle),
+                    wake_handle: wake_handle,
+                    overflowed: overflowed,
+                    stopped: stopped,
+                })
+            },
+            Ok(Err(err)) => {
+                let _ = handle.join();
+                Err(err)
+            },
+            Err(_) => {
+                let _ = handle.join();
+                Err(::error::with_message(::libc::EIO, String::from("monitor thread panicked")))
+            },
+        }
+    }
+
+    fn run<S: EventSender>(self, sender: S, ready: mpsc::Sender<::Result<WakeHandle>>, overflowed: Arc<AtomicBool>, stopped: Arc<AtomicBool>) -> ::Result<()> {
+        let mut socket = match self.listen() {
+            Ok(socket) => socket,
+            Err(err) => {
+                let _ = ready.send(Err(err));
+                return Ok(());
+            },
+        };
+
+        match socket.wake_handle() {
+            Ok(wake_handle) => {
+                let _ = ready.send(Ok(wake_handle));
+            },
+            Err(err) => {
+                let _ = ready.send(Err(err));
+                return Ok(());
+            },
+        }
+
+        for event in socket.iter() {
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+
+            match event {
+                Ok(event) => {
+                    if !deliver(&sender, event.snapshot(), &stopped) {
+                        break;
+                    }
+                },
+                Err(ref err) if err.kind() == ::ErrorKind::Overflow => {
+                    overflowed.store(true, Ordering::SeqCst);
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn listen(&self) -> ::Result<::MonitorSocket> {
+        let context = try!(Context::new());
+        let mut monitor = try!(Monitor::with_source(&context, self.source));
+
+        for filter in &self.filters {
+            try!(match *filter {
+                Filter::Subsystem(ref subsystem) => monitor.match_subsystem(subsystem),
+                Filter::SubsystemDevtype(ref subsystem, ref devtype) => monitor.match_subsystem_devtype(subsystem, devtype),
+                Filter::Tag(ref tag) => monitor.match_tag(tag),
+            });
+        }
+
+        if let Some(size) = self.receive_buffer_size {
+            try!(monitor.set_receive_buffer_size(size));
+        }
+
+        monitor.listen()
+    }
+}
+
+impl Default for MonitorThreadBuilder {
+    fn default() -> Self {
+        MonitorThreadBuilder::new()
+    }
+}
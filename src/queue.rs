@@ -0,0 +1,118 @@
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+
+use ::context::Context;
+use ::handle::Handle;
+
+
+/// The state of udev's event queue.
+///
+/// udev processes the events it receives from the kernel in a queue. A `Queue` reports whether the
+/// udev daemon is running and whether it has events left to process, and can wait until all
+/// queued events have been processed, like `udevadm settle`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// let context = libudev::Context::new().unwrap();
+/// let mut queue = libudev::Queue::new(&context).unwrap();
+///
+/// if queue.is_active() {
+///     queue.settle(Duration::from_secs(120)).unwrap();
+/// }
+/// ```
+pub struct Queue {
+    queue: *mut ::ffi::udev_queue,
+    _context: Context,
+}
+
+impl Drop for Queue {
+    fn drop(&mut self) {
+        unsafe {
+            ::ffi::udev_queue_unref(self.queue);
+        }
+    }
+}
+
+impl Queue {
+    /// Creates a new queue.
+    pub fn new(context: &Context) -> ::Result<Self> {
+        let queue = try_alloc!(unsafe {
+            ::ffi::udev_queue_new(context.as_ptr())
+        });
+
+        Ok(Queue {
+            queue: queue,
+            _context: context.clone(),
+        })
+    }
+
+    /// Checks whether the udev daemon is running.
+    pub fn is_active(&self) -> bool {
+        unsafe {
+            ::ffi::udev_queue_get_udev_is_active(self.queue) > 0
+        }
+    }
+
+    /// Checks whether the udev daemon has finished processing all queued events.
+    pub fn is_empty(&self) -> bool {
+        unsafe {
+            ::ffi::udev_queue_get_queue_is_empty(self.queue) > 0
+        }
+    }
+
+    /// Returns a file descriptor that becomes readable when the state of the queue changes.
+    ///
+    /// The file descriptor is created on the first call and is owned by the queue. After it becomes
+    /// readable, `flush()` must be called before waiting for the next change. Creating the file
+    /// descriptor fails if udev's runtime directory doesn't exist.
+    pub fn fd(&self) -> ::Result<RawFd> {
+        let fd = unsafe {
+            ::ffi::udev_queue_get_fd(self.queue)
+        };
+
+        if fd < 0 {
+            return Err(::error::from_errno(fd));
+        }
+
+        Ok(fd)
+    }
+
+    /// Clears the pending notifications of the file descriptor returned by `fd()`.
+    pub fn flush(&mut self) -> ::Result<()> {
+        ::util::errno_to_result(unsafe {
+            ::ffi::udev_queue_flush(self.queue)
+        })
+    }
+
+    /// Blocks until the queue is empty or the timeout expires.
+    ///
+    /// This method waits for notifications on the file descriptor returned by `fd()` instead of
+    /// polling the queue. If the queue isn't empty before the timeout expires, it returns an error
+    /// of kind `ErrorKind::Io(io::ErrorKind::TimedOut)`.
+    pub fn settle(&mut self, timeout: Duration) -> ::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let deadline = Instant::now() + timeout;
+        let fds = [try!(self.fd())];
+
+        loop {
+            try!(self.flush());
+
+            if self.is_empty() {
+                return Ok(());
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Err(::error::from_raw_os_error(::libc::ETIMEDOUT));
+            }
+
+            try!(::util::poll_readable(&fds, Some(deadline - now)));
+        }
+    }
+}
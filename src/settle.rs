@@ -0,0 +1,76 @@
+use std::future::Future;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+use tokio::io::unix::AsyncFd;
+
+use ::queue::Queue;
+
+
+/// A future that completes when udev's event queue is empty.
+///
+/// A `Settle` future registers the queue's file descriptor with the tokio reactor and checks the
+/// queue each time it changes. It is available with the `tokio` feature and is created by
+/// `Queue::settle_async()`, which must be called from within the context of a tokio runtime with
+/// I/O enabled.
+///
+/// The future doesn't time out by itself. It can be wrapped in `tokio::time::timeout()` to limit
+/// how long it waits.
+///
+/// ## Example
+///
+/// ```no_run
+/// # extern crate libudev;
+/// # extern crate tokio;
+/// # fn main() {
+/// # let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+/// # let _guard = runtime.enter();
+/// let context = libudev::Context::new().unwrap();
+/// let mut queue = libudev::Queue::new(&context).unwrap();
+///
+/// runtime.block_on(queue.settle_async().unwrap()).unwrap();
+/// # }
+/// ```
+pub struct Settle<'a> {
+    queue: &'a mut Queue,
+    fd: AsyncFd<RawFd>,
+}
+
+impl Queue {
+    /// Returns a future that completes when the queue is empty.
+    ///
+    /// This method must be called from within the context of a tokio runtime.
+    pub fn settle_async(&mut self) -> ::Result<Settle<'_>> {
+        let fd = try!(self.fd());
+
+        match AsyncFd::new(fd) {
+            Ok(fd) => Ok(Settle { queue: self, fd: fd }),
+            Err(err) => Err(::error::from_io_error(err)),
+        }
+    }
+}
+
+impl<'a> Future for Settle<'a> {
+    type Output = ::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<::Result<()>> {
+        let settle = self.get_mut();
+
+        loop {
+            if let Err(err) = settle.queue.flush() {
+                return Poll::Ready(Err(err));
+            }
+
+            if settle.queue.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+
+            match settle.fd.poll_read_ready(cx) {
+                Poll::Ready(Ok(mut guard)) => guard.clear_ready(),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(::error::from_io_error(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
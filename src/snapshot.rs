@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use libc::dev_t;
+
+use ::device::Device;
+use ::monitor::{Event, EventSource, EventType};
+
+
+/// An owned copy of a device's state.
+///
+/// A `DeviceSnapshot` captures a device's identity, properties, tags, devlinks, and optionally a
+/// selection of its attributes and the identity of its ancestors. Unlike a `Device`, it doesn't refer
+/// to any libudev objects, so it is `Send` and `Sync` and can be processed on other threads. With
+/// the `serde` feature, it can also be serialized and deserialized.
+///
+/// Strings and paths that are not valid UTF-8 are converted lossily, so that every snapshot can be
+/// serialized.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct DeviceSnapshot {
+    syspath: PathBuf,
+    devpath: String,
+    subsystem: Option<String>,
+    devtype: Option<String>,
+    sysname: Option<String>,
+    driver: Option<String>,
+    devnode: Option<PathBuf>,
+    devnum: Option<dev_t>,
+    action: Option<String>,
+    seqnum: Option<u64>,
+    properties: BTreeMap<String, String>,
+    attributes: BTreeMap<String, String>,
+    tags: Vec<String>,
+    devlinks: Vec<PathBuf>,
+    parents: Vec<ParentSnapshot>,
+}
+
+impl DeviceSnapshot {
+    /// Returns the syspath of the device.
+    pub fn syspath(&self) -> &Path {
+        &self.syspath
+    }
+
+    /// Returns the kernel devpath value of the device.
+    pub fn devpath(&self) -> &str {
+        &self.devpath
+    }
+
+    /// Returns the subsystem name of the device.
+    pub fn subsystem(&self) -> Option<&str> {
+        self.subsystem.as_deref()
+    }
+
+    /// Returns the devtype name of the device.
+    pub fn devtype(&self) -> Option<&str> {
+        self.devtype.as_deref()
+    }
+
+    /// Returns the kernel device name of the device.
+    pub fn sysname(&self) -> Option<&str> {
+        self.sysname.as_deref()
+    }
+
+    /// Returns the name of the kernel driver that was attached to the device.
+    pub fn driver(&self) -> Option<&str> {
+        self.driver.as_deref()
+    }
+
+    /// Returns the path to the device node belonging to the device.
+    pub fn devnode(&self) -> Option<&Path> {
+        self.devnode.as_deref()
+    }
+
+    /// Returns the device's major/minor number.
+    pub fn devnum(&self) -> Option<dev_t> {
+        self.devnum
+    }
+
+    /// Returns the action of the event that the device was received with.
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    /// Returns the sequence number of the event that the device was received with.
+    pub fn seqnum(&self) -> Option<u64> {
+        self.seqnum
+    }
+
+    /// Returns the device's properties.
+    pub fn properties(&self) -> &BTreeMap<String, String> {
+        &self.properties
+    }
+
+    /// Retrieves the value of a device property.
+    pub fn property_value(&self, property: &str) -> Option<&str> {
+        self.properties.get(property).map(String::as_str)
+    }
+
+    /// Returns the attributes that were captured in the snapshot.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Retrieves the value of a device attribute, if it was captured in the snapshot.
+    pub fn attribute_value(&self, attribute: &str) -> Option<&str> {
+        self.attributes.get(attribute).map(String::as_str)
+    }
+
+    /// Returns the device's tags.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns the device's devlinks.
+    pub fn devlinks(&self) -> &[PathBuf] {
+        &self.devlinks
+    }
+
+    /// Returns the snapshots of the device's ancestors, starting with its parent.
+    ///
+    /// Ancestors are only captured by `Device::snapshot_with_parents()` and
+    /// `Event::snapshot_with_parents()`. The slice is empty for other snapshots.
+    pub fn parents(&self) -> &[ParentSnapshot] {
+        &self.parents
+    }
+}
+
+
+/// An owned copy of the identity of one of a device's ancestors.
+///
+/// `ParentSnapshot`s are captured as part of a `DeviceSnapshot` and are returned by
+/// `DeviceSnapshot::parents()`. Like in a `DeviceSnapshot`, strings and paths that are not valid
+/// UTF-8 are converted lossily.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct ParentSnapshot {
+    syspath: PathBuf,
+    subsystem: Option<String>,
+    devtype: Option<String>,
+    sysname: Option<String>,
+    driver: Option<String>,
+}
+
+impl ParentSnapshot {
+    fn new(device: &Device) -> ParentSnapshot {
+        ParentSnapshot {
+            syspath: device.syspath().map(lossy_path).unwrap_or_default(),
+            subsystem: device.subsystem().map(lossy),
+            devtype: device.devtype().map(lossy),
+            sysname: device.sysname().map(lossy),
+            driver: device.driver().map(lossy),
+        }
+    }
+
+    /// Returns the syspath of the ancestor.
+    pub fn syspath(&self) -> &Path {
+        &self.syspath
+    }
+
+    /// Returns the subsystem name of the ancestor.
+    pub fn subsystem(&self) -> Option<&str> {
+        self.subsystem.as_deref()
+    }
+
+    /// Returns the devtype name of the ancestor.
+    pub fn devtype(&self) -> Option<&str> {
+        self.devtype.as_deref()
+    }
+
+    /// Returns the kernel device name of the ancestor.
+    pub fn sysname(&self) -> Option<&str> {
+        self.sysname.as_deref()
+    }
+
+    /// Returns the name of the kernel driver that was attached to the ancestor.
+    pub fn driver(&self) -> Option<&str> {
+        self.driver.as_deref()
+    }
+}
+
+impl Device {
+    /// Captures a snapshot of the device's state without any attributes or ancestors.
+    pub fn snapshot(&self) -> DeviceSnapshot {
+        let mut snapshot = self.identity_snapshot();
+
+        snapshot.driver = self.driver().map(lossy);
+        snapshot.devlinks = self.devlinks().map(lossy_path).collect();
+        snapshot
+    }
+
+    /// Captures a snapshot of the device's state including the given attributes.
+    ///
+    /// Attributes that the device doesn't have are left out of the snapshot.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # let mut context = libudev::Context::new().unwrap();
+    /// # let device = libudev::Device::from_syspath(&context, Path::new("/sys/bus/usb/devices/1-1")).unwrap();
+    /// let snapshot = device.snapshot_with_attributes(&["idVendor", "idProduct", "serial"]);
+    ///
+    /// println!("{:?}", snapshot.attribute_value("serial"));
+    /// ```
+    pub fn snapshot_with_attributes<I, T>(&self, attributes: I) -> DeviceSnapshot
+        where I: IntoIterator<Item = T>, T: AsRef<OsStr>
+    {
+        let mut snapshot = self.snapshot();
+
+        snapshot.attributes = attributes.into_iter().filter_map(|attribute| {
+            let attribute = attribute.as_ref();
+
+            self.attribute_value(attribute).map(|value| (lossy(attribute), lossy(value)))
+        }).collect();
+
+        snapshot
+    }
+
+    /// Captures a snapshot of the device's state including the identity of each of its ancestors.
+    ///
+    /// Every ancestor is looked up in `sysfs`, so this is more expensive than `snapshot()`.
+    pub fn snapshot_with_parents(&self) -> DeviceSnapshot {
+        let mut snapshot = self.snapshot();
+
+        snapshot.parents = self.ancestors().map(|parent| ParentSnapshot::new(&parent)).collect();
+        snapshot
+    }
+
+    fn identity_snapshot(&self) -> DeviceSnapshot {
+        DeviceSnapshot {
+            syspath: self.syspath().map(lossy_path).unwrap_or_default(),
+            devpath: self.devpath().map(lossy).unwrap_or_default(),
+            subsystem: self.subsystem().map(lossy),
+            devtype: self.devtype().map(lossy),
+            sysname: self.sysname().map(lossy),
+            driver: None,
+            devnode: self.devnode().map(lossy_path),
+            devnum: self.devnum(),
+            action: self.action().map(lossy),
+            seqnum: self.seqnum(),
+            properties: self.properties().map(|p| (lossy(p.name()), lossy(p.value()))).collect(),
+            attributes: BTreeMap::new(),
+            tags: self.tags().map(lossy).collect(),
+            devlinks: Vec::new(),
+            parents: Vec::new(),
+        }
+    }
+}
+
+/// An owned copy of an event.
+///
+/// An `EventSnapshot` is `Send` and `Sync`, so events can be received on one thread and processed
+/// on others. It provides access to a `DeviceSnapshot` of the event's device. With the `serde`
+/// feature, it can also be serialized and deserialized.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::sync::mpsc;
+/// # use std::thread;
+/// let (sender, receiver) = mpsc::channel::<libudev::EventSnapshot>();
+///
+/// thread::spawn(move || {
+///     for event in receiver {
+///         println!("{}: {}", event.event_type(), event.syspath().display());
+///     }
+/// });
+///
+/// let context = libudev::Context::new().unwrap();
+/// let mut socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+///
+/// for event in socket.iter() {
+///     sender.send(event.unwrap().snapshot()).unwrap();
+/// }
+/// ```
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct EventSnapshot {
+    source: EventSource,
+    seqnum: u64,
+    device: DeviceSnapshot,
+}
+
+/// Provides access to the snapshot of the device associated with the event.
+impl Deref for EventSnapshot {
+    type Target = DeviceSnapshot;
+
+    fn deref(&self) -> &DeviceSnapshot {
+        &self.device
+    }
+}
+
+impl EventSnapshot {
+    /// Returns the `EventType` corresponding to this event.
+    pub fn event_type(&self) -> EventType {
+        match self.device.action() {
+            Some(action) => ::monitor::event_type_from_action(OsStr::new(action)),
+            None => EventType::Unknown,
+        }
+    }
+
+    /// Returns the source of this event.
+    pub fn source(&self) -> EventSource {
+        self.source
+    }
+
+    /// Returns the event's sequence number.
+    pub fn sequence_number(&self) -> u64 {
+        self.seqnum
+    }
+
+    /// Returns the devpath the device had before it was moved.
+    pub fn devpath_old(&self) -> Option<&str> {
+        self.device.property_value("DEVPATH_OLD")
+    }
+
+    /// Returns the snapshot of the device associated with this event.
+    pub fn device(&self) -> &DeviceSnapshot {
+        &self.device
+    }
+
+    /// Converts the event snapshot into the snapshot of its device.
+    pub fn into_device(self) -> DeviceSnapshot {
+        self.device
+    }
+}
+
+impl Event {
+    /// Captures an owned copy of the event.
+    ///
+    /// The device snapshot only contains what the event carries: the device's identity, action,
+    /// sequence number, properties, and tags. The driver and devlinks are available as the `DRIVER`
+    /// and `DEVLINKS` properties.
+    pub fn snapshot(&self) -> EventSnapshot {
+        EventSnapshot {
+            source: self.source(),
+            seqnum: self.sequence_number(),
+            device: self.device().identity_snapshot(),
+        }
+    }
+
+    /// Captures an owned copy of the event including the identity of each of the device's
+    /// ancestors.
+    ///
+    /// The device snapshot is captured like with `Device::snapshot_with_parents()`, so every
+    /// ancestor is looked up in `sysfs`.
+    pub fn snapshot_with_parents(&self) -> EventSnapshot {
+        EventSnapshot {
+            source: self.source(),
+            seqnum: self.sequence_number(),
+            device: self.device().snapshot_with_parents(),
+        }
+    }
+}
+
+// Snapshots exist to be moved across threads, so make sure they stay `Send` and `Sync`.
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn send_sync<T: Send + Sync>() {}
+
+    send_sync::<DeviceSnapshot>();
+    send_sync::<EventSnapshot>();
+}
+
+fn lossy(s: &OsStr) -> String {
+    s.to_string_lossy().into_owned()
+}
+
+fn lossy_path(path: &Path) -> PathBuf {
+    PathBuf::from(lossy(path.as_os_str()))
+}
+
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    use ::monitor::EventSource;
+    use ::serde_json;
+
+    use super::{lossy_path, DeviceSnapshot, EventSnapshot, ParentSnapshot};
+
+    fn device_snapshot(devnode: &Path) -> DeviceSnapshot {
+        let mut properties = BTreeMap::new();
+        properties.insert(String::from("DEVNAME"), String::from("/dev/sda1"));
+        properties.insert(String::from("ID_FS_TYPE"), String::from("ext4"));
+
+        let mut attributes = BTreeMap::new();
+        attributes.insert(String::from("size"), String::from("2048"));
+
+        DeviceSnapshot {
+            syspath: PathBuf::from("/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda/sda1"),
+            devpath: String::from("/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda/sda1"),
+            subsystem: Some(String::from("block")),
+            devtype: Some(String::from("partition")),
+            sysname: Some(String::from("sda1")),
+            driver: None,
+            devnode: Some(devnode.to_path_buf()),
+            devnum: Some(::libc::makedev(8, 1)),
+            action: Some(String::from("change")),
+            seqnum: Some(4242),
+            properties: properties,
+            attributes: attributes,
+            tags: vec![String::from("systemd")],
+            devlinks: vec![PathBuf::from("/dev/disk/by-uuid/0f3c"), lossy_path(devnode)],
+            parents: vec![
+                ParentSnapshot {
+                    syspath: PathBuf::from("/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda"),
+                    subsystem: Some(String::from("block")),
+                    devtype: Some(String::from("disk")),
+                    sysname: Some(String::from("sda")),
+                    driver: None,
+                },
+                ParentSnapshot {
+                    syspath: PathBuf::from("/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0"),
+                    subsystem: Some(String::from("scsi")),
+                    devtype: Some(String::from("scsi_device")),
+                    sysname: Some(String::from("0:0:0:0")),
+                    driver: Some(String::from("sd")),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn device_snapshot_json_round_trip() {
+        let snapshot = device_snapshot(Path::new("/dev/sda1"));
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains(r#""devnode":"/dev/sda1""#));
+        assert!(json.contains(r#""properties":{"DEVNAME":"/dev/sda1","ID_FS_TYPE":"ext4"}"#));
+        assert!(json.contains(r#""devtype":"scsi_device""#));
+
+        let parsed: DeviceSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, snapshot);
+    }
+
+    #[test]
+    fn event_snapshot_json_round_trip() {
+        let snapshot = EventSnapshot {
+            source: EventSource::Udev,
+            seqnum: 4242,
+            device: device_snapshot(Path::new("/dev/sda1")),
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains(r#""source":"Udev""#));
+        assert!(json.contains(r#""seqnum":4242"#));
+
+        let parsed: EventSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, snapshot);
+        assert_eq!(parsed.sysname(), Some("sda1"));
+    }
+
+    #[test]
+    fn non_utf8_paths_serialize_lossily() {
+        let raw = Path::new(OsStr::from_bytes(b"/dev/disk/by-label/caf\xe9"));
+
+        // serde can't serialize paths that aren't valid UTF-8, which is why snapshots convert
+        // them.
+        assert!(serde_json::to_string(raw).is_err());
+
+        let snapshot = device_snapshot(&lossy_path(raw));
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"/dev/disk/by-label/caf\u{fffd}\""));
+
+        let parsed: DeviceSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, snapshot);
+        assert_eq!(parsed.devnode(), Some(Path::new("/dev/disk/by-label/caf\u{fffd}")));
+    }
+}
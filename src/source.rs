@@ -0,0 +1,28 @@
+use std::io;
+
+use std::os::unix::io::AsRawFd;
+
+use mio::{Interest, Registry, Token};
+use mio::event::Source;
+use mio::unix::SourceFd;
+
+use ::monitor::MonitorSocket;
+
+
+/// Allows a `MonitorSocket` to be registered with a `mio::Poll`.
+///
+/// `mio` delivers edge-triggered readiness events, so the socket should be drained with
+/// `MonitorSocket::drain()` each time it is reported as readable.
+impl Source for MonitorSocket {
+    fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
@@ -0,0 +1,100 @@
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+use futures_core::Stream;
+use tokio::io::unix::AsyncFd;
+
+use ::monitor::{Event, MonitorSocket};
+
+
+/// An asynchronous stream of events from a `MonitorSocket`.
+///
+/// A `MonitorStream` registers the socket's file descriptor with the tokio reactor and yields
+/// events as the socket becomes readable. It is available with the `tokio` feature and must be
+/// created from within the context of a tokio runtime with I/O enabled.
+///
+/// Events are only read from the socket while the stream is being polled. When the consumer is
+/// slow, pending events stay queued in the kernel's socket buffer instead of accumulating in
+/// memory. Dropping the stream, or a future that is waiting on it, does not lose any event that
+/// has already been received by the socket.
+///
+/// The stream yields an error if receiving an event fails or if the reactor reports an error while
+/// waiting for the socket to become readable. It can continue to be polled after an error. Lost
+/// events are reported as an error of kind `ErrorKind::Overflow`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # extern crate futures;
+/// # extern crate libudev;
+/// # extern crate tokio;
+/// # use futures::{future, StreamExt};
+/// # fn main() {
+/// # let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+/// # let _guard = runtime.enter();
+/// # let context = libudev::Context::new().unwrap();
+/// let socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+/// let events = libudev::MonitorStream::new(socket).unwrap();
+///
+/// runtime.block_on(events.for_each(|event| {
+///     match event {
+///         Ok(event) => println!("{}: {:?}", event.event_type(), event.syspath()),
+///         Err(err) => println!("error: {}", err),
+///     }
+///
+///     future::ready(())
+/// }));
+/// # }
+/// ```
+pub struct MonitorStream {
+    inner: AsyncFd<MonitorSocket>,
+}
+
+impl MonitorStream {
+    /// Creates a stream that receives events from the given socket.
+    ///
+    /// This method must be called from within the context of a tokio runtime.
+    pub fn new(socket: MonitorSocket) -> ::Result<Self> {
+        match AsyncFd::new(socket) {
+            Ok(inner) => Ok(MonitorStream { inner: inner }),
+            Err(err) => Err(::error::from_io_error(err)),
+        }
+    }
+
+    /// Returns a reference to the underlying socket.
+    pub fn get_ref(&self) -> &MonitorSocket {
+        self.inner.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying socket.
+    pub fn get_mut(&mut self) -> &mut MonitorSocket {
+        self.inner.get_mut()
+    }
+
+    /// Deregisters the socket from the reactor and returns it.
+    pub fn into_inner(self) -> MonitorSocket {
+        self.inner.into_inner()
+    }
+}
+
+impl Stream for MonitorStream {
+    type Item = ::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Option<::Result<Event>>> {
+        let stream = self.get_mut();
+
+        loop {
+            let mut guard = match stream.inner.poll_read_ready_mut(cx) {
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(::error::from_io_error(err)))),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match guard.get_inner_mut().try_receive_event() {
+                Ok(Some(event)) => return Poll::Ready(Some(Ok(event))),
+                Ok(None) => guard.clear_ready(),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
@@ -0,0 +1,756 @@
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CStr, CString, OsStr};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+
+use libc::{c_char, c_int, c_uint, c_ulonglong, dev_t, EINVAL, EIO, ENODEV, ENOENT};
+
+use ::db::{device_id, Database, DeviceRecord};
+
+use super::{fnmatch, option_as_ptr, ptr_to_os_str, set_errno, to_cstring, udev, udev_list_entry, List, SYS_PATH, DEV_PATH};
+
+
+pub struct udev_device {
+    refcount: Cell<usize>,
+    udev: *mut udev,
+    syspath: CString,
+    devpath: CString,
+    sysname: CString,
+    sysnum: Option<CString>,
+    subsystem: Option<CString>,
+    devtype: Option<CString>,
+    driver: Option<CString>,
+    devnode: Option<CString>,
+    devnum: dev_t,
+    ifindex: Option<u32>,
+    action: Option<CString>,
+    seqnum: u64,
+    uevent: Vec<(CString, CString)>,
+    read_db: bool,
+    db: OnceCell<Option<DeviceRecord>>,
+    properties: OnceCell<List>,
+    tags: OnceCell<List>,
+    current_tags: OnceCell<List>,
+    devlinks: OnceCell<List>,
+    sysattrs: OnceCell<List>,
+    sysattr_values: RefCell<HashMap<CString, Option<CString>>>,
+    parent: OnceCell<*mut udev_device>,
+}
+
+impl udev_device {
+    fn property(&self, key: &[u8]) -> Option<&CStr> {
+        self.properties().iter().find(|entry| entry.name.as_bytes() == key).and_then(|entry| entry.value.as_ref()).map(|value| value.as_c_str())
+    }
+
+    fn db(&self) -> Option<&DeviceRecord> {
+        self.db.get_or_init(|| {
+            let subsystem = self.subsystem.as_ref().map(|s| OsStr::from_bytes(s.as_bytes()));
+            let sysname = OsStr::from_bytes(self.sysname.as_bytes());
+            let devnum = if self.devnum != 0 { Some(self.devnum) } else { None };
+
+            let devpath = OsStr::from_bytes(self.devpath.as_bytes());
+
+            match device_id(subsystem, Some(sysname), Some(devpath), devnum, self.ifindex) {
+                Some(ref id) if self.read_db => Database::new().record(id).ok().and_then(|record| record),
+                _ => None,
+            }
+        }).as_ref()
+    }
+
+    fn properties(&self) -> &List {
+        self.properties.get_or_init(|| {
+            let mut properties: BTreeMap<CString, CString> = self.uevent.iter().cloned().collect();
+
+            if let Some(db) = self.db() {
+                properties.extend(db.properties().iter().map(|&(ref key, ref value)| (to_cstring(key.as_bytes()), to_cstring(value.as_bytes()))));
+
+                let devlinks: Vec<PathBuf> = db.devlinks().iter().map(|devlink| Path::new(DEV_PATH).join(devlink)).collect();
+
+                if !devlinks.is_empty() {
+                    properties.insert(to_cstring("DEVLINKS"), join(devlinks.iter().map(|devlink| devlink.as_os_str()), b" ", b""));
+                }
+
+                if !db.tags().is_empty() {
+                    properties.insert(to_cstring("TAGS"), join(db.tags().iter().map(|tag| tag.as_os_str()), b":", b":"));
+                }
+
+                // Records that were written before udev supported current tags don't have any.
+                if !db.current_tags().is_empty() {
+                    properties.insert(to_cstring("CURRENT_TAGS"), join(db.current_tags().iter().map(|tag| tag.as_os_str()), b":", b":"));
+                }
+
+                if let Some(usec) = db.usec_initialized() {
+                    properties.insert(to_cstring("USEC_INITIALIZED"), to_cstring(usec.to_string()));
+                }
+            }
+
+            List::new(properties.into_iter().map(|(key, value)| (key, Some(value))))
+        })
+    }
+
+    fn tags(&self) -> &List {
+        self.tags.get_or_init(|| split_list(self.property(b"TAGS"), b':'))
+    }
+
+    fn current_tags(&self) -> &List {
+        self.current_tags.get_or_init(|| {
+            match self.property(b"CURRENT_TAGS") {
+                Some(tags) => split_list(Some(tags), b':'),
+                None => split_list(self.property(b"TAGS"), b':'),
+            }
+        })
+    }
+
+    fn devlinks(&self) -> &List {
+        self.devlinks.get_or_init(|| split_list(self.property(b"DEVLINKS"), b' '))
+    }
+
+    fn sysattrs(&self) -> &List {
+        self.sysattrs.get_or_init(|| {
+            let mut names = Vec::new();
+
+            read_sysattrs(Path::new(OsStr::from_bytes(self.syspath.as_bytes())), Path::new(""), &mut names);
+            names.sort();
+
+            List::new(names.into_iter().map(|name| (name, None)))
+        })
+    }
+
+    fn sysattr_path(&self, sysattr: &CStr) -> PathBuf {
+        Path::new(OsStr::from_bytes(self.syspath.as_bytes())).join(OsStr::from_bytes(sysattr.to_bytes()))
+    }
+}
+
+impl Drop for udev_device {
+    fn drop(&mut self) {
+        if let Some(&parent) = self.parent.get() {
+            unsafe {
+                udev_device_unref(parent);
+            }
+        }
+    }
+}
+
+/// Creates a device from the properties of its uevent.
+///
+/// The properties must include `DEVPATH`. When `read_db` is true, the device's properties are
+/// completed with its record in udev's database.
+pub fn device_new(udev: *mut udev, properties: Vec<(Vec<u8>, Vec<u8>)>, read_db: bool) -> Option<*mut udev_device> {
+    let mut uevent = Vec::with_capacity(properties.len());
+
+    let mut devpath = None;
+    let mut subsystem = None;
+    let mut devtype = None;
+    let mut driver = None;
+    let mut devnode = None;
+    let mut major = None;
+    let mut minor = None;
+    let mut ifindex = None;
+    let mut action = None;
+    let mut seqnum = 0;
+
+    for (key, mut value) in properties {
+        match &key[..] {
+            b"DEVPATH" => devpath = Some(to_cstring(&value)),
+            b"SUBSYSTEM" => subsystem = Some(to_cstring(&value)),
+            b"DEVTYPE" => devtype = Some(to_cstring(&value)),
+            b"DRIVER" => driver = Some(to_cstring(&value)),
+            b"MAJOR" => major = parse::<c_uint>(&value),
+            b"MINOR" => minor = parse::<c_uint>(&value),
+            b"IFINDEX" => ifindex = parse::<u32>(&value),
+            b"ACTION" => action = Some(to_cstring(&value)),
+            b"SEQNUM" => seqnum = parse::<u64>(&value).unwrap_or(0),
+            b"DEVNAME" => {
+                if !value.starts_with(b"/") {
+                    let mut absolute = DEV_PATH.as_bytes().to_vec();
+                    absolute.push(b'/');
+                    absolute.extend_from_slice(&value);
+                    value = absolute;
+                }
+
+                devnode = Some(to_cstring(&value));
+            },
+            _ => (),
+        }
+
+        uevent.push((to_cstring(key), to_cstring(value)));
+    }
+
+    let devpath = devpath?;
+
+    let mut syspath = SYS_PATH.as_bytes().to_vec();
+    syspath.extend_from_slice(devpath.as_bytes());
+
+    let sysname: Vec<u8> = match syspath.iter().rposition(|&b| b == b'/') {
+        Some(i) => syspath[i + 1..].iter().map(|&b| if b == b'!' { b'/' } else { b }).collect(),
+        None => Vec::new(),
+    };
+
+    let digits = sysname.iter().rev().take_while(|b| b.is_ascii_digit()).count();
+    let sysnum = if digits > 0 { Some(to_cstring(&sysname[sysname.len() - digits..])) } else { None };
+
+    let devnum = match (major, minor) {
+        (Some(major), Some(minor)) => ::libc::makedev(major, minor),
+        _ => 0,
+    };
+
+    let device = udev_device {
+        refcount: Cell::new(1),
+        udev: udev,
+        syspath: to_cstring(syspath),
+        devpath: devpath,
+        sysname: to_cstring(sysname),
+        sysnum: sysnum,
+        subsystem: subsystem,
+        devtype: devtype,
+        driver: driver,
+        devnode: devnode,
+        devnum: devnum,
+        ifindex: ifindex,
+        action: action,
+        seqnum: seqnum,
+        uevent: uevent,
+        read_db: read_db,
+        db: OnceCell::new(),
+        properties: OnceCell::new(),
+        tags: OnceCell::new(),
+        current_tags: OnceCell::new(),
+        devlinks: OnceCell::new(),
+        sysattrs: OnceCell::new(),
+        sysattr_values: RefCell::new(HashMap::new()),
+        parent: OnceCell::new(),
+    };
+
+    Some(Box::into_raw(Box::new(device)))
+}
+
+/// Reads a device from sysfs.
+fn read_device(udev: *mut udev, syspath: &Path) -> Result<*mut udev_device, c_int> {
+    if !syspath.starts_with(SYS_PATH) {
+        return Err(EINVAL);
+    }
+
+    let syspath = try!(fs::canonicalize(syspath).map_err(|err| errno(&err)));
+
+    let devpath = match syspath.strip_prefix(SYS_PATH) {
+        Ok(devpath) if !devpath.as_os_str().is_empty() => Path::new("/").join(devpath),
+        _ => return Err(ENODEV),
+    };
+
+    if devpath.starts_with("/devices") {
+        if !syspath.join("uevent").exists() {
+            return Err(ENODEV);
+        }
+    }
+    else if !syspath.is_dir() {
+        return Err(ENODEV);
+    }
+
+    let mut properties = vec![(b"DEVPATH".to_vec(), devpath.as_os_str().as_bytes().to_vec())];
+
+    if let Ok(contents) = fs::read(syspath.join("uevent")) {
+        for line in contents.split(|&b| b == b'\n') {
+            if let Some(i) = line.iter().position(|&b| b == b'=') {
+                properties.push((line[..i].to_vec(), line[i + 1..].to_vec()));
+            }
+        }
+    }
+
+    let subsystem = match link_name(&syspath.join("subsystem")) {
+        Some(subsystem) => Some(subsystem),
+        None if devpath.starts_with("/module") => Some(b"module".to_vec()),
+        None if devpath.iter().any(|component| component == "drivers") => Some(b"drivers".to_vec()),
+        None if devpath.starts_with("/subsystem") || devpath.starts_with("/class") || devpath.starts_with("/bus") => Some(b"subsystem".to_vec()),
+        None => None,
+    };
+
+    if let Some(subsystem) = subsystem {
+        properties.push((b"SUBSYSTEM".to_vec(), subsystem));
+    }
+
+    if !properties.iter().any(|&(ref key, _)| key == b"DRIVER") {
+        if let Some(driver) = link_name(&syspath.join("driver")) {
+            properties.push((b"DRIVER".to_vec(), driver));
+        }
+    }
+
+    device_new(udev, properties, true).ok_or(ENODEV)
+}
+
+/// Adds the names of the attributes in a device's directory to a list.
+///
+/// Subdirectories are searched recursively, except for those that belong to child devices.
+fn read_sysattrs(syspath: &Path, subdir: &Path, names: &mut Vec<CString>) {
+    let entries = match fs::read_dir(syspath.join(subdir)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+
+        let name = subdir.join(entry.file_name());
+
+        if file_type.is_dir() {
+            if !entry.path().join("uevent").exists() {
+                read_sysattrs(syspath, &name, names);
+            }
+        }
+        else if file_type.is_file() || file_type.is_symlink() {
+            names.push(to_cstring(name.as_os_str().as_bytes()));
+        }
+    }
+}
+
+/// Returns the file name of a symlink's target.
+fn link_name(path: &Path) -> Option<Vec<u8>> {
+    fs::read_link(path).ok().and_then(|target| target.file_name().map(|name| name.as_bytes().to_vec()))
+}
+
+fn parse<T: ::std::str::FromStr>(value: &[u8]) -> Option<T> {
+    ::std::str::from_utf8(value).ok().and_then(|s| s.parse().ok())
+}
+
+fn errno(err: &io::Error) -> c_int {
+    err.raw_os_error().unwrap_or(EIO)
+}
+
+fn trim_newlines(mut value: Vec<u8>) -> CString {
+    while value.last().is_some_and(|&b| b == b'\n' || b == b'\r') {
+        value.pop();
+    }
+
+    to_cstring(value)
+}
+
+fn join<'a, I: Iterator<Item = &'a OsStr>>(items: I, separator: &[u8], affix: &[u8]) -> CString {
+    let mut joined = affix.to_vec();
+
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            joined.extend_from_slice(separator);
+        }
+
+        joined.extend_from_slice(item.as_bytes());
+    }
+
+    joined.extend_from_slice(affix);
+
+    to_cstring(joined)
+}
+
+/// Splits a property's value into a sorted list of unique items.
+fn split_list(value: Option<&CStr>, separator: u8) -> List {
+    let mut items: Vec<CString> = match value {
+        Some(value) => value.to_bytes().split(|&b| b == separator).filter(|item| !item.is_empty()).map(to_cstring).collect(),
+        None => Vec::new(),
+    };
+
+    items.sort();
+    items.dedup();
+
+    List::new(items.into_iter().map(|item| (item, None)))
+}
+
+fn result(device: Result<*mut udev_device, c_int>) -> *mut udev_device {
+    match device {
+        Ok(device) => device,
+        Err(errno) => {
+            set_errno(errno);
+            ptr::null_mut()
+        },
+    }
+}
+
+
+pub unsafe fn udev_device_ref(udev_device: *mut udev_device) -> *mut udev_device {
+    if !udev_device.is_null() {
+        (*udev_device).refcount.set((*udev_device).refcount.get() + 1);
+    }
+
+    udev_device
+}
+
+pub unsafe fn udev_device_unref(udev_device: *mut udev_device) -> *mut udev_device {
+    if !udev_device.is_null() {
+        let refcount = (*udev_device).refcount.get() - 1;
+        (*udev_device).refcount.set(refcount);
+
+        if refcount == 0 {
+            drop(Box::from_raw(udev_device));
+        }
+    }
+
+    ptr::null_mut()
+}
+
+pub unsafe fn udev_device_get_udev(udev_device: *mut udev_device) -> *mut udev {
+    (*udev_device).udev
+}
+
+pub unsafe fn udev_device_new_from_syspath(udev: *mut udev, syspath: *const c_char) -> *mut udev_device {
+    match ptr_to_os_str(syspath) {
+        Some(syspath) => result(read_device(udev, Path::new(syspath))),
+        None => result(Err(EINVAL)),
+    }
+}
+
+pub unsafe fn udev_device_new_from_devnum(udev: *mut udev, dev_type: c_char, devnum: dev_t) -> *mut udev_device {
+    let kind = match dev_type as u8 {
+        b'b' => "block",
+        b'c' => "char",
+        _ => return result(Err(EINVAL)),
+    };
+
+    let syspath = format!("{}/dev/{}/{}:{}", SYS_PATH, kind, ::libc::major(devnum), ::libc::minor(devnum));
+
+    result(read_device(udev, Path::new(&syspath)))
+}
+
+pub unsafe fn udev_device_new_from_subsystem_sysname(udev: *mut udev, subsystem: *const c_char, sysname: *const c_char) -> *mut udev_device {
+    let (subsystem, sysname) = match (ptr_to_os_str(subsystem), ptr_to_os_str(sysname)) {
+        (Some(subsystem), Some(sysname)) => (subsystem, sysname),
+        _ => return result(Err(EINVAL)),
+    };
+
+    let sys = Path::new(SYS_PATH);
+    let name = OsStr::from_bytes(&sysname.as_bytes().iter().map(|&b| if b == b'/' { b'!' } else { b }).collect::<Vec<u8>>()).to_os_string();
+
+    let candidates = match subsystem.as_bytes() {
+        b"subsystem" => vec![sys.join("bus").join(&name), sys.join("class").join(&name)],
+        b"module" => vec![sys.join("module").join(&name)],
+        b"drivers" => {
+            match name.as_bytes().iter().position(|&b| b == b':') {
+                Some(i) => {
+                    let bus = OsStr::from_bytes(&name.as_bytes()[..i]);
+                    let driver = OsStr::from_bytes(&name.as_bytes()[i + 1..]);
+
+                    vec![sys.join("bus").join(bus).join("drivers").join(driver)]
+                },
+                None => return result(Err(EINVAL)),
+            }
+        },
+        _ => vec![
+            sys.join("bus").join(subsystem).join("devices").join(&name),
+            sys.join("class").join(subsystem).join(&name),
+            sys.join("firmware").join(subsystem).join(&name),
+        ],
+    };
+
+    for candidate in candidates {
+        if fs::symlink_metadata(&candidate).is_ok() {
+            return result(read_device(udev, &candidate));
+        }
+    }
+
+    result(Err(ENODEV))
+}
+
+pub unsafe fn udev_device_new_from_device_id(udev: *mut udev, id: *const c_char) -> *mut udev_device {
+    let id = match ptr_to_os_str(id) {
+        Some(id) if id.len() > 1 => id.as_bytes(),
+        _ => return result(Err(EINVAL)),
+    };
+
+    match id[0] {
+        b'b' | b'c' => {
+            let devnum = ::std::str::from_utf8(&id[1..]).ok().and_then(|devnum| {
+                let mut parts = devnum.splitn(2, ':');
+
+                match (parts.next().and_then(|s| s.parse().ok()), parts.next().and_then(|s| s.parse().ok())) {
+                    (Some(major), Some(minor)) => Some(::libc::makedev(major, minor)),
+                    _ => None,
+                }
+            });
+
+            match devnum {
+                Some(devnum) => udev_device_new_from_devnum(udev, id[0] as c_char, devnum),
+                None => result(Err(EINVAL)),
+            }
+        },
+        b'n' => {
+            let mut name = [0 as c_char; ::libc::IF_NAMESIZE];
+
+            let ifindex = match parse::<c_uint>(&id[1..]) {
+                Some(ifindex) => ifindex,
+                None => return result(Err(EINVAL)),
+            };
+
+            if ::libc::if_indextoname(ifindex, name.as_mut_ptr()).is_null() {
+                return result(Err(ENODEV));
+            }
+
+            let device = udev_device_new_from_subsystem_sysname(udev, b"net\0".as_ptr() as *const c_char, name.as_ptr());
+
+            // The interface may have been renamed and its name reused by another one.
+            if !device.is_null() && (*device).ifindex != Some(ifindex) {
+                udev_device_unref(device);
+                return result(Err(ENODEV));
+            }
+
+            device
+        },
+        b'+' => {
+            match id[1..].iter().position(|&b| b == b':') {
+                Some(i) => {
+                    let subsystem = to_cstring(&id[1..i + 1]);
+                    let sysname = to_cstring(&id[i + 2..]);
+
+                    udev_device_new_from_subsystem_sysname(udev, subsystem.as_ptr(), sysname.as_ptr())
+                },
+                None => result(Err(EINVAL)),
+            }
+        },
+        _ => result(Err(EINVAL)),
+    }
+}
+
+pub unsafe fn udev_device_get_parent(udev_device: *mut udev_device) -> *mut udev_device {
+    let device = &*udev_device;
+
+    let parent = *device.parent.get_or_init(|| {
+        let mut path = Path::new(OsStr::from_bytes(device.syspath.as_bytes()));
+
+        while let Some(dir) = path.parent() {
+            if !dir.starts_with(Path::new(SYS_PATH).join("devices")) || dir == Path::new(SYS_PATH).join("devices") {
+                break;
+            }
+
+            if let Ok(parent) = read_device(device.udev, dir) {
+                return parent;
+            }
+
+            path = dir;
+        }
+
+        ptr::null_mut()
+    });
+
+    if parent.is_null() {
+        set_errno(ENOENT);
+    }
+
+    parent
+}
+
+pub unsafe fn udev_device_get_parent_with_subsystem_devtype(udev_device: *mut udev_device, subsystem: *const c_char, devtype: *const c_char) -> *mut udev_device {
+    let subsystem = match ptr_to_os_str(subsystem) {
+        Some(subsystem) => subsystem.as_bytes(),
+        None => return result(Err(EINVAL)),
+    };
+
+    let devtype = ptr_to_os_str(devtype).map(|devtype| devtype.as_bytes());
+
+    let mut parent = udev_device_get_parent(udev_device);
+
+    while !parent.is_null() {
+        let device = &*parent;
+
+        let subsystem_matches = device.subsystem.as_ref().is_some_and(|s| s.as_bytes() == subsystem);
+        let devtype_matches = devtype.is_none_or(|devtype| device.devtype.as_ref().is_some_and(|d| d.as_bytes() == devtype));
+
+        if subsystem_matches && devtype_matches {
+            return parent;
+        }
+
+        parent = udev_device_get_parent(parent);
+    }
+
+    result(Err(ENOENT))
+}
+
+pub unsafe fn udev_device_get_devpath(udev_device: *mut udev_device) -> *const c_char {
+    (*udev_device).devpath.as_ptr()
+}
+
+pub unsafe fn udev_device_get_subsystem(udev_device: *mut udev_device) -> *const c_char {
+    option_as_ptr(&(*udev_device).subsystem)
+}
+
+pub unsafe fn udev_device_get_devtype(udev_device: *mut udev_device) -> *const c_char {
+    option_as_ptr(&(*udev_device).devtype)
+}
+
+pub unsafe fn udev_device_get_syspath(udev_device: *mut udev_device) -> *const c_char {
+    (*udev_device).syspath.as_ptr()
+}
+
+pub unsafe fn udev_device_get_sysname(udev_device: *mut udev_device) -> *const c_char {
+    (*udev_device).sysname.as_ptr()
+}
+
+pub unsafe fn udev_device_get_sysnum(udev_device: *mut udev_device) -> *const c_char {
+    option_as_ptr(&(*udev_device).sysnum)
+}
+
+pub unsafe fn udev_device_get_devnode(udev_device: *mut udev_device) -> *const c_char {
+    option_as_ptr(&(*udev_device).devnode)
+}
+
+pub unsafe fn udev_device_get_is_initialized(udev_device: *mut udev_device) -> c_int {
+    let device = &*udev_device;
+
+    (device.property(b"USEC_INITIALIZED").is_some() || device.db().is_some()) as c_int
+}
+
+pub unsafe fn udev_device_get_devlinks_list_entry(udev_device: *mut udev_device) -> *mut udev_list_entry {
+    (*udev_device).devlinks().head()
+}
+
+pub unsafe fn udev_device_get_properties_list_entry(udev_device: *mut udev_device) -> *mut udev_list_entry {
+    (*udev_device).properties().head()
+}
+
+pub unsafe fn udev_device_get_tags_list_entry(udev_device: *mut udev_device) -> *mut udev_list_entry {
+    (*udev_device).tags().head()
+}
+
+pub unsafe fn udev_device_get_current_tags_list_entry(udev_device: *mut udev_device) -> *mut udev_list_entry {
+    (*udev_device).current_tags().head()
+}
+
+pub unsafe fn udev_device_get_property_value(udev_device: *mut udev_device, key: *const c_char) -> *const c_char {
+    match ptr_to_os_str(key).and_then(|key| (*udev_device).property(key.as_bytes())) {
+        Some(value) => value.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+pub unsafe fn udev_device_get_driver(udev_device: *mut udev_device) -> *const c_char {
+    option_as_ptr(&(*udev_device).driver)
+}
+
+pub unsafe fn udev_device_get_devnum(udev_device: *mut udev_device) -> dev_t {
+    (*udev_device).devnum
+}
+
+pub unsafe fn udev_device_get_action(udev_device: *mut udev_device) -> *const c_char {
+    option_as_ptr(&(*udev_device).action)
+}
+
+pub unsafe fn udev_device_get_sysattr_value(udev_device: *mut udev_device, sysattr: *const c_char) -> *const c_char {
+    let device = &*udev_device;
+
+    let sysattr = match ptr_to_os_str(sysattr) {
+        Some(sysattr) => to_cstring(sysattr.as_bytes()),
+        None => {
+            set_errno(EINVAL);
+            return ptr::null();
+        },
+    };
+
+    let mut values = device.sysattr_values.borrow_mut();
+
+    let value = values.entry(sysattr).or_insert_with_key(|sysattr| {
+        let path = device.sysattr_path(sysattr);
+
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => return None,
+        };
+
+        // Only a few links are attributes, whose values are the names of their targets.
+        if metadata.file_type().is_symlink() {
+            match sysattr.to_bytes() {
+                b"driver" | b"subsystem" | b"module" => link_name(&path).map(to_cstring),
+                _ => None,
+            }
+        }
+        else if metadata.is_file() && metadata.permissions().mode() & 0o400 != 0 {
+            fs::read(&path).ok().map(trim_newlines)
+        }
+        else {
+            None
+        }
+    });
+
+    match *value {
+        Some(ref value) => value.as_ptr(),
+        None => {
+            set_errno(ENOENT);
+            ptr::null()
+        },
+    }
+}
+
+pub unsafe fn udev_device_set_sysattr_value(udev_device: *mut udev_device, sysattr: *const c_char, value: *mut c_char) -> c_int {
+    let device = &*udev_device;
+
+    let (sysattr, value) = match (ptr_to_os_str(sysattr), ptr_to_os_str(value)) {
+        (Some(sysattr), Some(value)) => (to_cstring(sysattr.as_bytes()), value.as_bytes()),
+        _ => return -EINVAL,
+    };
+
+    let path = device.sysattr_path(&sysattr);
+
+    match fs::write(&path, value) {
+        Ok(()) => {
+            device.sysattr_values.borrow_mut().insert(sysattr, Some(trim_newlines(value.to_vec())));
+
+            0
+        },
+        Err(err) => {
+            device.sysattr_values.borrow_mut().remove(&sysattr);
+
+            -errno(&err)
+        },
+    }
+}
+
+pub unsafe fn udev_device_get_sysattr_list_entry(udev_device: *mut udev_device) -> *mut udev_list_entry {
+    (*udev_device).sysattrs().head()
+}
+
+pub unsafe fn udev_device_get_seqnum(udev_device: *mut udev_device) -> c_ulonglong {
+    (*udev_device).seqnum as c_ulonglong
+}
+
+pub unsafe fn udev_device_get_usec_since_initialized(udev_device: *mut udev_device) -> c_ulonglong {
+    let initialized = match (*udev_device).property(b"USEC_INITIALIZED").and_then(|usec| parse::<u64>(usec.to_bytes())) {
+        Some(usec) => usec,
+        None => return 0,
+    };
+
+    let mut now: ::libc::timespec = ::std::mem::zeroed();
+    ::libc::clock_gettime(::libc::CLOCK_MONOTONIC, &mut now);
+
+    let now = now.tv_sec as u64 * 1_000_000 + now.tv_nsec as u64 / 1_000;
+
+    now.saturating_sub(initialized) as c_ulonglong
+}
+
+pub unsafe fn udev_device_has_tag(udev_device: *mut udev_device, tag: *const c_char) -> c_int {
+    match ptr_to_os_str(tag) {
+        Some(tag) => (*udev_device).tags().find(&to_cstring(tag.as_bytes())).is_some() as c_int,
+        None => 0,
+    }
+}
+
+pub unsafe fn udev_device_has_current_tag(udev_device: *mut udev_device, tag: *const c_char) -> c_int {
+    match ptr_to_os_str(tag) {
+        Some(tag) => (*udev_device).current_tags().find(&to_cstring(tag.as_bytes())).is_some() as c_int,
+        None => 0,
+    }
+}
+
+/// Returns whether the device needs to be initialized by udev before it can be used, which is the
+/// case for devices with device nodes or network interfaces.
+pub fn device_needs_initialization(device: &udev_device) -> bool {
+    device.devnum != 0 || device.ifindex.is_some()
+}
+
+/// Returns whether the device matches a glob pattern for one of its properties.
+pub fn device_match_property(device: &udev_device, key: &CStr, value: &CStr) -> bool {
+    device.properties().iter().any(|entry| {
+        fnmatch(key, &entry.name) && entry.value.as_ref().is_some_and(|v| fnmatch(value, v))
+    })
+}
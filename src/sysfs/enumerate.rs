@@ -0,0 +1,327 @@
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::ffi::{CStr, CString, OsStr};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use std::os::unix::ffi::OsStrExt;
+
+use libc::{c_char, c_int, EINVAL};
+
+use super::device::{device_match_property, device_needs_initialization};
+use super::{fnmatch, ptr_to_os_str, set_errno, to_cstring, udev, udev_list_entry, List, SYS_PATH};
+use super::{udev_device, udev_device_unref, udev_device_new_from_syspath, udev_device_get_syspath, udev_device_get_subsystem, udev_device_get_sysname, udev_device_get_sysattr_value, udev_device_has_tag, udev_device_get_is_initialized};
+
+
+pub struct udev_enumerate {
+    refcount: Cell<usize>,
+    udev: *mut udev,
+    match_subsystem: Vec<CString>,
+    nomatch_subsystem: Vec<CString>,
+    match_sysattr: Vec<(CString, Option<CString>)>,
+    nomatch_sysattr: Vec<(CString, Option<CString>)>,
+    match_property: Vec<(CString, CString)>,
+    match_tag: Vec<CString>,
+    match_sysname: Vec<CString>,
+    match_parent: Option<PathBuf>,
+    match_is_initialized: bool,
+    syspaths: Vec<PathBuf>,
+    list: List,
+}
+
+impl udev_enumerate {
+    fn subsystem_matches(&self, subsystem: &CStr) -> bool {
+        if self.nomatch_subsystem.iter().any(|pattern| fnmatch(pattern, subsystem)) {
+            return false;
+        }
+
+        self.match_subsystem.is_empty() || self.match_subsystem.iter().any(|pattern| fnmatch(pattern, subsystem))
+    }
+
+    unsafe fn device_matches(&self, device: *mut udev_device) -> bool {
+        let subsystem = match cstr(udev_device_get_subsystem(device)) {
+            Some(subsystem) => subsystem,
+            None => return false,
+        };
+
+        if !self.subsystem_matches(subsystem) {
+            return false;
+        }
+
+        if !self.match_sysname.is_empty() {
+            let sysname = CStr::from_ptr(udev_device_get_sysname(device));
+
+            if !self.match_sysname.iter().any(|pattern| fnmatch(pattern, sysname)) {
+                return false;
+            }
+        }
+
+        if !self.match_sysattr.iter().all(|&(ref sysattr, ref value)| sysattr_matches(device, sysattr, value)) {
+            return false;
+        }
+
+        if self.nomatch_sysattr.iter().any(|&(ref sysattr, ref value)| sysattr_matches(device, sysattr, value)) {
+            return false;
+        }
+
+        if !self.match_property.is_empty() && !self.match_property.iter().any(|&(ref key, ref value)| device_match_property(&*device, key, value)) {
+            return false;
+        }
+
+        if !self.match_tag.iter().all(|tag| udev_device_has_tag(device, tag.as_ptr()) != 0) {
+            return false;
+        }
+
+        if self.match_is_initialized && device_needs_initialization(&*device) && udev_device_get_is_initialized(device) == 0 {
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns the syspaths of the devices that could match the enumerator.
+    fn candidates(&self) -> BTreeSet<PathBuf> {
+        let mut candidates = BTreeSet::new();
+
+        match self.match_parent {
+            Some(ref parent) => scan_tree(parent, &mut candidates),
+            None => {
+                let sys = Path::new(SYS_PATH);
+
+                for &(dir, subdir) in &[("bus", Some("devices")), ("class", None)] {
+                    let subsystems = match fs::read_dir(sys.join(dir)) {
+                        Ok(subsystems) => subsystems,
+                        Err(_) => continue,
+                    };
+
+                    for subsystem in subsystems.filter_map(|entry| entry.ok()) {
+                        if !self.subsystem_matches(&to_cstring(subsystem.file_name().as_bytes())) {
+                            continue;
+                        }
+
+                        let path = match subdir {
+                            Some(subdir) => subsystem.path().join(subdir),
+                            None => subsystem.path(),
+                        };
+
+                        let devices = match fs::read_dir(path) {
+                            Ok(devices) => devices,
+                            Err(_) => continue,
+                        };
+
+                        for device in devices.filter_map(|entry| entry.ok()) {
+                            if let Ok(syspath) = fs::canonicalize(device.path()) {
+                                candidates.insert(syspath);
+                            }
+                        }
+                    }
+                }
+            },
+        }
+
+        candidates
+    }
+}
+
+/// Adds the syspaths of all devices below a directory to a set, including the directory itself.
+fn scan_tree(dir: &Path, syspaths: &mut BTreeSet<PathBuf>) {
+    if dir.join("uevent").exists() {
+        syspaths.insert(dir.to_path_buf());
+    }
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                scan_tree(&entry.path(), syspaths);
+            }
+        }
+    }
+}
+
+unsafe fn sysattr_matches(device: *mut udev_device, sysattr: &CStr, pattern: &Option<CString>) -> bool {
+    match cstr(udev_device_get_sysattr_value(device, sysattr.as_ptr())) {
+        Some(value) => pattern.as_ref().is_none_or(|pattern| fnmatch(pattern, value)),
+        None => false,
+    }
+}
+
+unsafe fn cstr<'a>(ptr: *const c_char) -> Option<&'a CStr> {
+    if !ptr.is_null() {
+        Some(CStr::from_ptr(ptr))
+    }
+    else {
+        None
+    }
+}
+
+/// Returns whether a device should be listed after other devices, because it usually depends on
+/// them. This is the case for RAID and device mapper devices.
+fn delay_device(syspath: &Path) -> bool {
+    let syspath = syspath.as_os_str().as_bytes();
+
+    [&b"/block/md"[..], &b"/block/dm-"[..]].iter().any(|pattern| {
+        syspath.windows(pattern.len()).any(|window| window == *pattern)
+    })
+}
+
+unsafe fn add_match(list: &mut Vec<CString>, value: *const c_char) -> c_int {
+    match ptr_to_os_str(value) {
+        Some(value) => {
+            list.push(to_cstring(value.as_bytes()));
+            0
+        },
+        None => -EINVAL,
+    }
+}
+
+unsafe fn add_match_sysattr(list: &mut Vec<(CString, Option<CString>)>, sysattr: *const c_char, value: *const c_char) -> c_int {
+    match ptr_to_os_str(sysattr) {
+        Some(sysattr) => {
+            list.push((to_cstring(sysattr.as_bytes()), ptr_to_os_str(value).map(|value| to_cstring(value.as_bytes()))));
+            0
+        },
+        None => -EINVAL,
+    }
+}
+
+
+pub unsafe fn udev_enumerate_new(udev: *mut udev) -> *mut udev_enumerate {
+    if udev.is_null() {
+        set_errno(EINVAL);
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(udev_enumerate {
+        refcount: Cell::new(1),
+        udev: udev,
+        match_subsystem: Vec::new(),
+        nomatch_subsystem: Vec::new(),
+        match_sysattr: Vec::new(),
+        nomatch_sysattr: Vec::new(),
+        match_property: Vec::new(),
+        match_tag: Vec::new(),
+        match_sysname: Vec::new(),
+        match_parent: None,
+        match_is_initialized: false,
+        syspaths: Vec::new(),
+        list: List::new(Vec::new()),
+    }))
+}
+
+pub unsafe fn udev_enumerate_unref(udev_enumerate: *mut udev_enumerate) -> *mut udev_enumerate {
+    if !udev_enumerate.is_null() {
+        let refcount = (*udev_enumerate).refcount.get() - 1;
+        (*udev_enumerate).refcount.set(refcount);
+
+        if refcount == 0 {
+            drop(Box::from_raw(udev_enumerate));
+        }
+    }
+
+    ptr::null_mut()
+}
+
+pub unsafe fn udev_enumerate_get_udev(udev_enumerate: *mut udev_enumerate) -> *mut udev {
+    (*udev_enumerate).udev
+}
+
+pub unsafe fn udev_enumerate_add_match_subsystem(udev_enumerate: *mut udev_enumerate, subsystem: *const c_char) -> c_int {
+    add_match(&mut (*udev_enumerate).match_subsystem, subsystem)
+}
+
+pub unsafe fn udev_enumerate_add_nomatch_subsystem(udev_enumerate: *mut udev_enumerate, subsystem: *const c_char) -> c_int {
+    add_match(&mut (*udev_enumerate).nomatch_subsystem, subsystem)
+}
+
+pub unsafe fn udev_enumerate_add_match_sysattr(udev_enumerate: *mut udev_enumerate, sysattr: *const c_char, value: *const c_char) -> c_int {
+    add_match_sysattr(&mut (*udev_enumerate).match_sysattr, sysattr, value)
+}
+
+pub unsafe fn udev_enumerate_add_nomatch_sysattr(udev_enumerate: *mut udev_enumerate, sysattr: *const c_char, value: *const c_char) -> c_int {
+    add_match_sysattr(&mut (*udev_enumerate).nomatch_sysattr, sysattr, value)
+}
+
+pub unsafe fn udev_enumerate_add_match_property(udev_enumerate: *mut udev_enumerate, property: *const c_char, value: *const c_char) -> c_int {
+    match (ptr_to_os_str(property), ptr_to_os_str(value)) {
+        (Some(property), Some(value)) => {
+            (*udev_enumerate).match_property.push((to_cstring(property.as_bytes()), to_cstring(value.as_bytes())));
+            0
+        },
+        _ => -EINVAL,
+    }
+}
+
+pub unsafe fn udev_enumerate_add_match_tag(udev_enumerate: *mut udev_enumerate, tag: *const c_char) -> c_int {
+    add_match(&mut (*udev_enumerate).match_tag, tag)
+}
+
+pub unsafe fn udev_enumerate_add_match_parent(udev_enumerate: *mut udev_enumerate, parent: *mut udev_device) -> c_int {
+    if parent.is_null() {
+        return -EINVAL;
+    }
+
+    let syspath = OsStr::from_bytes(CStr::from_ptr(udev_device_get_syspath(parent)).to_bytes());
+    (*udev_enumerate).match_parent = Some(PathBuf::from(syspath));
+
+    0
+}
+
+pub unsafe fn udev_enumerate_add_match_is_initialized(udev_enumerate: *mut udev_enumerate) -> c_int {
+    (*udev_enumerate).match_is_initialized = true;
+
+    0
+}
+
+pub unsafe fn udev_enumerate_add_match_sysname(udev_enumerate: *mut udev_enumerate, sysname: *const c_char) -> c_int {
+    add_match(&mut (*udev_enumerate).match_sysname, sysname)
+}
+
+pub unsafe fn udev_enumerate_add_syspath(udev_enumerate: *mut udev_enumerate, syspath: *const c_char) -> c_int {
+    let device = udev_device_new_from_syspath((*udev_enumerate).udev, syspath);
+
+    if device.is_null() {
+        return -*::libc::__errno_location();
+    }
+
+    let syspath = OsStr::from_bytes(CStr::from_ptr(udev_device_get_syspath(device)).to_bytes());
+    (*udev_enumerate).syspaths.push(PathBuf::from(syspath));
+
+    udev_device_unref(device);
+
+    0
+}
+
+pub unsafe fn udev_enumerate_scan_devices(udev_enumerate: *mut udev_enumerate) -> c_int {
+    let enumerate = &mut *udev_enumerate;
+
+    let mut syspaths: BTreeSet<PathBuf> = enumerate.syspaths.iter().cloned().collect();
+
+    for candidate in enumerate.candidates() {
+        if syspaths.contains(&candidate) {
+            continue;
+        }
+
+        let syspath = to_cstring(candidate.as_os_str().as_bytes());
+        let device = udev_device_new_from_syspath(enumerate.udev, syspath.as_ptr());
+
+        if !device.is_null() {
+            if enumerate.device_matches(device) {
+                syspaths.insert(candidate);
+            }
+
+            udev_device_unref(device);
+        }
+    }
+
+    let (delayed, mut syspaths): (Vec<PathBuf>, Vec<PathBuf>) = syspaths.into_iter().partition(|syspath| delay_device(syspath));
+    syspaths.extend(delayed);
+
+    enumerate.list = List::new(syspaths.into_iter().map(|syspath| (to_cstring(syspath.as_os_str().as_bytes()), None)));
+
+    0
+}
+
+pub unsafe fn udev_enumerate_get_list_entry(udev_enumerate: *mut udev_enumerate) -> *mut udev_list_entry {
+    (*udev_enumerate).list.head()
+}
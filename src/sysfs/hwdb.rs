@@ -0,0 +1,633 @@
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::ptr;
+
+use libc::{c_char, c_uint, EINVAL, ENODATA, ENOENT};
+
+use super::{fnmatch, set_errno, to_cstring, udev, udev_list_entry, List};
+
+
+// The hardware database is read from the `hwdb.bin` file that's compiled by `systemd-hwdb`. It
+// holds a trie of modalias patterns. Every node has a prefix that's shared by all of its
+// descendants, a sorted array of child entries that each continue the pattern with one more
+// character, and an array of value entries for the patterns that end at the node. All integers are
+// little-endian, and strings are offsets to nul-terminated strings in the same file.
+//
+// Lookups walk the trie like `sd-hwdb` does: plain characters are followed exactly, and once a
+// pattern reaches a glob character, the rest of the subtree is matched with `fnmatch()`. Matching
+// properties are collected in a map that's sorted by name, like the list that libudev returns, so
+// the order in which `sd-hwdb` finds them isn't kept.
+
+const HWDB_PATHS: &[&str] = &[
+    "/etc/systemd/hwdb/hwdb.bin",
+    "/etc/udev/hwdb.bin",
+    "/usr/lib/systemd/hwdb/hwdb.bin",
+    "/lib/systemd/hwdb/hwdb.bin",
+    "/usr/lib/udev/hwdb.bin",
+];
+
+const SIGNATURE: &[u8] = b"KSLPHHRH";
+
+// Sizes of the header and of the value entries that carry the file and line they came from.
+const HEADER_SIZE: usize = 80;
+const VALUE_ENTRY2_SIZE: usize = 32;
+
+
+pub struct udev_hwdb {
+    trie: Trie,
+    list: List,
+}
+
+pub unsafe fn udev_hwdb_unref(hwdb: *mut udev_hwdb) -> *mut udev_hwdb {
+    if !hwdb.is_null() {
+        drop(Box::from_raw(hwdb));
+    }
+
+    ptr::null_mut()
+}
+
+pub unsafe fn udev_hwdb_new(udev: *mut udev) -> *mut udev_hwdb {
+    if udev.is_null() {
+        set_errno(EINVAL);
+        return ptr::null_mut();
+    }
+
+    let data = match read_hwdb() {
+        Ok(data) => data,
+        Err(err) => {
+            set_errno(err.raw_os_error().unwrap_or(EINVAL));
+            return ptr::null_mut();
+        },
+    };
+
+    match Trie::new(data) {
+        Some(trie) => {
+            Box::into_raw(Box::new(udev_hwdb {
+                trie: trie,
+                list: List::new(Vec::new()),
+            }))
+        },
+        None => {
+            set_errno(EINVAL);
+            ptr::null_mut()
+        },
+    }
+}
+
+pub unsafe fn udev_hwdb_get_properties_list_entry(hwdb: *mut udev_hwdb, modalias: *const c_char, _flags: c_uint) -> *mut udev_list_entry {
+    if hwdb.is_null() || modalias.is_null() {
+        set_errno(EINVAL);
+        return ptr::null_mut();
+    }
+
+    let hwdb = &mut *hwdb;
+    let trie = &hwdb.trie;
+
+    let mut properties = BTreeMap::new();
+
+    if trie.search(CStr::from_ptr(modalias).to_bytes(), &mut properties).is_none() {
+        hwdb.list = List::new(Vec::new());
+        set_errno(EINVAL);
+        return ptr::null_mut();
+    }
+
+    hwdb.list = List::new(properties.into_iter().map(|(key, value)| {
+        (to_cstring(key), Some(to_cstring(trie.string(value.value_off).unwrap_or(b""))))
+    }));
+
+    if hwdb.list.entries.is_empty() {
+        set_errno(ENODATA);
+    }
+
+    hwdb.list.head()
+}
+
+
+/// Reads the first database that exists in one of the search paths.
+fn read_hwdb() -> io::Result<Vec<u8>> {
+    for path in HWDB_PATHS {
+        match fs::read(path) {
+            Ok(data) => return Ok(data),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(io::Error::from_raw_os_error(ENOENT))
+}
+
+struct Trie {
+    data: Vec<u8>,
+    node_size: usize,
+    child_entry_size: usize,
+    value_entry_size: usize,
+    root_off: usize,
+}
+
+#[derive(Clone,Copy)]
+struct Node {
+    off: usize,
+    prefix_off: usize,
+    children_count: usize,
+    values_count: usize,
+}
+
+/// A value entry of a node. The file priority and line number are only stored by newer versions
+/// of `systemd-hwdb`, and are zero otherwise.
+#[derive(Clone,Copy)]
+struct Value {
+    value_off: usize,
+    filename_off: usize,
+    line_number: u32,
+    file_priority: u16,
+}
+
+impl Trie {
+    fn new(data: Vec<u8>) -> Option<Trie> {
+        if data.len() < HEADER_SIZE || &data[..SIGNATURE.len()] != SIGNATURE {
+            return None;
+        }
+
+        let mut trie = Trie {
+            data: data,
+            node_size: 0,
+            child_entry_size: 0,
+            value_entry_size: 0,
+            root_off: 0,
+        };
+
+        if trie.u64_at(16)? != trie.data.len() as u64 {
+            return None;
+        }
+
+        trie.node_size = trie.usize_at(32)?;
+        trie.child_entry_size = trie.usize_at(40)?;
+        trie.value_entry_size = trie.usize_at(48)?;
+        trie.root_off = trie.usize_at(56)?;
+
+        if trie.node_size < 24 || trie.child_entry_size < 16 || trie.value_entry_size < 16 {
+            return None;
+        }
+
+        Some(trie)
+    }
+
+    fn bytes(&self, off: usize, len: usize) -> Option<&[u8]> {
+        self.data.get(off..off.checked_add(len)?)
+    }
+
+    fn u64_at(&self, off: usize) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(off, 8)?);
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    fn usize_at(&self, off: usize) -> Option<usize> {
+        self.u64_at(off).and_then(|n| if n <= usize::MAX as u64 { Some(n as usize) } else { None })
+    }
+
+    fn string(&self, off: usize) -> Option<&[u8]> {
+        let bytes = self.data.get(off..)?;
+        let len = bytes.iter().position(|&b| b == 0)?;
+
+        Some(&bytes[..len])
+    }
+
+    /// Returns the prefix of a node. Nodes without a prefix have an offset of zero or point to an
+    /// empty string.
+    fn prefix(&self, node: &Node) -> Option<&[u8]> {
+        if node.prefix_off == 0 {
+            return Some(b"");
+        }
+
+        self.string(node.prefix_off)
+    }
+
+    fn node(&self, off: usize) -> Option<Node> {
+        Some(Node {
+            off: off,
+            prefix_off: self.usize_at(off)?,
+            children_count: *self.data.get(off.checked_add(8)?)? as usize,
+            values_count: self.usize_at(off.checked_add(16)?)?,
+        })
+    }
+
+    fn child(&self, node: &Node, index: usize) -> Option<(u8, Node)> {
+        let off = node.off.checked_add(self.node_size)?.checked_add(index.checked_mul(self.child_entry_size)?)?;
+
+        let c = *self.data.get(off)?;
+        let child = self.node(self.usize_at(off.checked_add(8)?)?)?;
+
+        Some((c, child))
+    }
+
+    /// Finds the child that continues the node's pattern with `c`. The child entries are sorted by
+    /// their character.
+    fn lookup_child(&self, node: &Node, c: u8) -> Option<Option<Node>> {
+        let (mut low, mut high) = (0, node.children_count);
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (child_c, child) = self.child(node, mid)?;
+
+            if child_c == c {
+                return Some(Some(child));
+            }
+            else if child_c < c {
+                low = mid + 1;
+            }
+            else {
+                high = mid;
+            }
+        }
+
+        Some(None)
+    }
+
+    fn value(&self, node: &Node, index: usize) -> Option<(&[u8], Value)> {
+        let children_size = node.children_count.checked_mul(self.child_entry_size)?;
+        let off = node.off.checked_add(self.node_size)?.checked_add(children_size)?.checked_add(index.checked_mul(self.value_entry_size)?)?;
+
+        let key = self.string(self.usize_at(off)?)?;
+
+        let mut value = Value {
+            value_off: self.usize_at(off + 8)?,
+            filename_off: 0,
+            line_number: 0,
+            file_priority: 0,
+        };
+
+        if self.value_entry_size >= VALUE_ENTRY2_SIZE {
+            let bytes = self.bytes(off + 16, 16)?;
+
+            value.filename_off = self.usize_at(off + 16)?;
+            value.line_number = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+            value.file_priority = u16::from_le_bytes([bytes[12], bytes[13]]);
+        }
+
+        Some((key, value))
+    }
+
+    /// Collects the properties of all patterns that match `search`.
+    fn search<'a>(&'a self, search: &[u8], properties: &mut BTreeMap<&'a [u8], Value>) -> Option<()> {
+        let mut buf = Vec::new();
+        let mut node = Some(self.node(self.root_off)?);
+        let mut i = 0;
+
+        while let Some(current) = node {
+            {
+                let prefix = self.prefix(&current)?;
+
+                for (p, &c) in prefix.iter().enumerate() {
+                    if c == b'*' || c == b'?' || c == b'[' {
+                        return self.fnmatch(&current, p, &mut buf, &search[i + p..], properties);
+                    }
+
+                    if search.get(i + p) != Some(&c) {
+                        return Some(());
+                    }
+                }
+
+                i += prefix.len();
+            }
+
+            for &glob in b"*?[" {
+                if let Some(child) = self.lookup_child(&current, glob)? {
+                    buf.push(glob);
+                    self.fnmatch(&child, 0, &mut buf, &search[i..], properties)?;
+                    buf.pop();
+                }
+            }
+
+            if i == search.len() {
+                for n in 0..current.values_count {
+                    self.add_property(&current, n, properties)?;
+                }
+
+                return Some(());
+            }
+
+            node = self.lookup_child(&current, search[i])?;
+            i += 1;
+        }
+
+        Some(())
+    }
+
+    /// Matches the patterns of a node's subtree against the rest of the search string. `buf` holds
+    /// the pattern from its first glob character up to the node, and the node's prefix is added to
+    /// it from position `p`.
+    fn fnmatch<'a>(&'a self, node: &Node, p: usize, buf: &mut Vec<u8>, search: &[u8], properties: &mut BTreeMap<&'a [u8], Value>) -> Option<()> {
+        let prefix = self.prefix(node)?.get(p..).unwrap_or(b"");
+        buf.extend_from_slice(prefix);
+
+        for n in 0..node.children_count {
+            let (c, child) = self.child(node, n)?;
+
+            buf.push(c);
+            self.fnmatch(&child, 0, buf, search, properties)?;
+            buf.pop();
+        }
+
+        if node.values_count > 0 && fnmatch(&to_cstring(&buf[..]), &to_cstring(search)) {
+            for n in 0..node.values_count {
+                self.add_property(node, n, properties)?;
+            }
+        }
+
+        let len = buf.len() - prefix.len();
+        buf.truncate(len);
+
+        Some(())
+    }
+
+    /// Adds a value entry to the properties. Keys that don't start with a space are reserved for
+    /// other uses and are skipped. When a key is set more than once, the entry from the file with
+    /// the higher priority wins, and then the one from the later line.
+    fn add_property<'a>(&'a self, node: &Node, index: usize, properties: &mut BTreeMap<&'a [u8], Value>) -> Option<()> {
+        let (key, value) = self.value(node, index)?;
+
+        let key = match key.split_first() {
+            Some((&b' ', key)) => key,
+            _ => return Some(()),
+        };
+
+        if self.value_entry_size >= VALUE_ENTRY2_SIZE {
+            if let Some(old) = properties.get(key) {
+                // Databases written before file priorities existed sort their file names by
+                // priority instead.
+                let older = if value.file_priority == 0 {
+                    (value.filename_off, value.line_number) < (old.filename_off, old.line_number)
+                }
+                else {
+                    (value.file_priority, value.line_number) < (old.file_priority, old.line_number)
+                };
+
+                if older {
+                    return Some(());
+                }
+            }
+        }
+
+        properties.insert(key, value);
+
+        Some(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::collections::HashMap;
+
+    use super::{Trie, HEADER_SIZE, SIGNATURE, VALUE_ENTRY2_SIZE};
+
+    /// An entry of a database: a pattern, a key, a value, a file name, a line number, and a file
+    /// priority.
+    type Entry<'a> = (&'a str, &'a str, &'a str, &'a str, u32, u16);
+
+    /// Compiles entries into a database like `systemd-hwdb` does. Nodes are compressed, so that
+    /// every node's prefix holds the characters that all patterns below it share.
+    struct Builder {
+        data: Vec<u8>,
+        strings: HashMap<Vec<u8>, usize>,
+        value_entry_size: usize,
+    }
+
+    impl Builder {
+        fn build(entries: &[Entry], value_entry_size: usize) -> Vec<u8> {
+            let mut builder = Builder {
+                data: vec![0; HEADER_SIZE],
+                strings: HashMap::new(),
+                value_entry_size: value_entry_size,
+            };
+
+            // The offsets of the file names follow the order of the names.
+            let mut filenames: Vec<&str> = entries.iter().map(|entry| entry.3).collect();
+            filenames.sort();
+
+            for filename in filenames {
+                builder.string(filename.as_bytes());
+            }
+
+            let patterns: Vec<(&[u8], &Entry)> = entries.iter().map(|entry| (entry.0.as_bytes(), entry)).collect();
+            let root_off = builder.node(&patterns);
+            let file_size = builder.data.len() as u64;
+
+            let header = &mut builder.data;
+            header[..8].copy_from_slice(SIGNATURE);
+            header[16..24].copy_from_slice(&file_size.to_le_bytes());
+            header[24..32].copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+            header[32..40].copy_from_slice(&24u64.to_le_bytes());
+            header[40..48].copy_from_slice(&16u64.to_le_bytes());
+            header[48..56].copy_from_slice(&(value_entry_size as u64).to_le_bytes());
+            header[56..64].copy_from_slice(&(root_off as u64).to_le_bytes());
+
+            builder.data
+        }
+
+        fn string(&mut self, s: &[u8]) -> usize {
+            if let Some(&off) = self.strings.get(s) {
+                return off;
+            }
+
+            let off = self.data.len();
+            self.data.extend_from_slice(s);
+            self.data.push(0);
+            self.strings.insert(s.to_vec(), off);
+
+            off
+        }
+
+        /// Writes the node for the patterns that remain below it and returns its offset.
+        fn node(&mut self, patterns: &[(&[u8], &Entry)]) -> usize {
+            let prefix_len = if patterns.iter().any(|&(pattern, _)| pattern.is_empty()) {
+                0
+            }
+            else {
+                let first = patterns[0].0;
+                (0..first.len()).take_while(|&i| patterns.iter().all(|&(pattern, _)| pattern.get(i) == Some(&first[i]))).count()
+            };
+
+            let mut children: BTreeMap<u8, Vec<(&[u8], &Entry)>> = BTreeMap::new();
+            let mut values = Vec::new();
+
+            for &(pattern, entry) in patterns {
+                match pattern[prefix_len..].split_first() {
+                    Some((&c, rest)) => children.entry(c).or_default().push((rest, entry)),
+                    None => values.push(entry),
+                }
+            }
+
+            let children: Vec<(u8, usize)> = children.iter().map(|(&c, patterns)| (c, self.node(patterns))).collect();
+            let prefix_off = self.string(&patterns[0].0[..prefix_len]);
+
+            let values: Vec<Vec<u8>> = values.iter().map(|&&(_, key, value, filename, line_number, file_priority)| {
+                let mut entry = Vec::new();
+                entry.extend_from_slice(&(self.string(key.as_bytes()) as u64).to_le_bytes());
+                entry.extend_from_slice(&(self.string(value.as_bytes()) as u64).to_le_bytes());
+
+                if self.value_entry_size >= VALUE_ENTRY2_SIZE {
+                    entry.extend_from_slice(&(self.string(filename.as_bytes()) as u64).to_le_bytes());
+                    entry.extend_from_slice(&line_number.to_le_bytes());
+                    entry.extend_from_slice(&file_priority.to_le_bytes());
+                    entry.extend_from_slice(&[0; 2]);
+                }
+
+                entry
+            }).collect();
+
+            let off = self.data.len();
+
+            self.data.extend_from_slice(&(prefix_off as u64).to_le_bytes());
+            self.data.push(children.len() as u8);
+            self.data.extend_from_slice(&[0; 7]);
+            self.data.extend_from_slice(&(values.len() as u64).to_le_bytes());
+
+            for (c, child_off) in children {
+                self.data.push(c);
+                self.data.extend_from_slice(&[0; 7]);
+                self.data.extend_from_slice(&(child_off as u64).to_le_bytes());
+            }
+
+            for value in values {
+                self.data.extend_from_slice(&value);
+            }
+
+            off
+        }
+    }
+
+    fn lookup(trie: &Trie, modalias: &str) -> Option<Vec<(String, String)>> {
+        let mut properties = BTreeMap::new();
+        trie.search(modalias.as_bytes(), &mut properties)?;
+
+        Some(properties.iter().map(|(&key, value)| {
+            (String::from_utf8_lossy(key).into_owned(), String::from_utf8_lossy(trie.string(value.value_off).unwrap()).into_owned())
+        }).collect())
+    }
+
+    fn property(name: &str, value: &str) -> (String, String) {
+        (String::from(name), String::from(value))
+    }
+
+    const ENTRIES: &[Entry] = &[
+        ("usb:v1D6Bp0002", " ID_MODEL_FROM_DATABASE", "2.0 root hub", "20-usb-vendor-model.hwdb", 10, 20),
+        ("usb:v1D6Bp0003", " ID_MODEL_FROM_DATABASE", "3.0 root hub", "20-usb-vendor-model.hwdb", 11, 20),
+        ("usb:v1D6B*", " ID_VENDOR_FROM_DATABASE", "Linux Foundation", "20-usb-vendor-model.hwdb", 5, 20),
+        ("usb:v046DpC52[AB]*", " ID_INPUT_MOUSE", "1", "70-mouse.hwdb", 3, 70),
+        ("usb:v046Dp*", " ID_VENDOR_FROM_DATABASE", "Logitech, Inc.", "20-usb-vendor-model.hwdb", 1, 20),
+        ("usb:v046DpC52B*", " ID_VENDOR_FROM_DATABASE", "Logitech (local)", "90-local.hwdb", 2, 90),
+        ("usb:v046DpC52?*", " ID_VENDOR_FROM_DATABASE", "Logitech (system)", "60-system.hwdb", 9, 60),
+        ("usb:v046DpC52B*", "RESERVED", "ignored", "20-usb-vendor-model.hwdb", 4, 20),
+        ("pci:v00008086d*", " ID_VENDOR_FROM_DATABASE", "Intel Corporation", "20-pci-vendor-model.hwdb", 7, 20),
+        ("pci:v00008086d*", " ID_VENDOR_FROM_DATABASE", "Intel Corp.", "20-pci-vendor-model.hwdb", 8, 20),
+    ];
+
+    #[test]
+    fn exact_match() {
+        for &value_entry_size in &[16, VALUE_ENTRY2_SIZE] {
+            let trie = Trie::new(Builder::build(ENTRIES, value_entry_size)).unwrap();
+
+            assert_eq!(lookup(&trie, "usb:v1D6Bp0003"), Some(vec![
+                property("ID_MODEL_FROM_DATABASE", "3.0 root hub"),
+                property("ID_VENDOR_FROM_DATABASE", "Linux Foundation"),
+            ]));
+
+            assert_eq!(lookup(&trie, "usb:v1D6Bp000"), Some(vec![property("ID_VENDOR_FROM_DATABASE", "Linux Foundation")]));
+            assert_eq!(lookup(&trie, "usb:v1D6C"), Some(vec![]));
+            assert_eq!(lookup(&trie, ""), Some(vec![]));
+        }
+    }
+
+    #[test]
+    fn glob_match() {
+        for &value_entry_size in &[16, VALUE_ENTRY2_SIZE] {
+            let trie = Trie::new(Builder::build(ENTRIES, value_entry_size)).unwrap();
+
+            assert_eq!(lookup(&trie, "usb:v046DpC52Ad0101"), Some(vec![
+                property("ID_INPUT_MOUSE", "1"),
+                property("ID_VENDOR_FROM_DATABASE", "Logitech (system)"),
+            ]));
+
+            assert_eq!(lookup(&trie, "usb:v046DpC530"), Some(vec![property("ID_VENDOR_FROM_DATABASE", "Logitech, Inc.")]));
+            assert_eq!(lookup(&trie, "pci:v00008087d0000"), Some(vec![]));
+        }
+    }
+
+    #[test]
+    fn higher_priority_overrides() {
+        let trie = Trie::new(Builder::build(ENTRIES, VALUE_ENTRY2_SIZE)).unwrap();
+
+        // Matched by three entries from files with priorities 20, 60, and 90.
+        assert_eq!(lookup(&trie, "usb:v046DpC52B"), Some(vec![
+            property("ID_INPUT_MOUSE", "1"),
+            property("ID_VENDOR_FROM_DATABASE", "Logitech (local)"),
+        ]));
+    }
+
+    #[test]
+    fn later_line_overrides() {
+        let trie = Trie::new(Builder::build(ENTRIES, VALUE_ENTRY2_SIZE)).unwrap();
+
+        assert_eq!(lookup(&trie, "pci:v00008086d00001237"), Some(vec![property("ID_VENDOR_FROM_DATABASE", "Intel Corp.")]));
+
+        // Without priorities, files are ordered by name.
+        let entries: Vec<Entry> = ENTRIES.iter().map(|&(pattern, key, value, filename, line_number, _)| (pattern, key, value, filename, line_number, 0)).collect();
+        let trie = Trie::new(Builder::build(&entries, VALUE_ENTRY2_SIZE)).unwrap();
+
+        assert_eq!(lookup(&trie, "usb:v046DpC52B"), Some(vec![
+            property("ID_INPUT_MOUSE", "1"),
+            property("ID_VENDOR_FROM_DATABASE", "Logitech (local)"),
+        ]));
+    }
+
+    #[test]
+    fn later_entry_overrides_without_file_names() {
+        let trie = Trie::new(Builder::build(ENTRIES, 16)).unwrap();
+
+        assert_eq!(lookup(&trie, "pci:v00008086d00001237"), Some(vec![property("ID_VENDOR_FROM_DATABASE", "Intel Corp.")]));
+    }
+
+    #[test]
+    fn bad_signature() {
+        let mut data = Builder::build(ENTRIES, VALUE_ENTRY2_SIZE);
+        data[0] = b'X';
+
+        assert!(Trie::new(data).is_none());
+    }
+
+    #[test]
+    fn truncated_file() {
+        let data = Builder::build(ENTRIES, VALUE_ENTRY2_SIZE);
+
+        assert!(Trie::new(data[..data.len() - 1].to_vec()).is_none());
+        assert!(Trie::new(data[..HEADER_SIZE - 1].to_vec()).is_none());
+
+        // Files whose size matches the header but whose nodes are cut off are rejected when they're
+        // searched.
+        for len in HEADER_SIZE..data.len() {
+            let mut truncated = data[..len].to_vec();
+            truncated[16..24].copy_from_slice(&(len as u64).to_le_bytes());
+
+            if let Some(trie) = Trie::new(truncated) {
+                for modalias in &["usb:v1D6Bp0003", "usb:v046DpC52B", "pci:v00008086d00001237"] {
+                    lookup(&trie, modalias);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bad_root_offset() {
+        let mut data = Builder::build(ENTRIES, VALUE_ENTRY2_SIZE);
+        let len = data.len() as u64;
+        data[56..64].copy_from_slice(&len.to_le_bytes());
+
+        let trie = Trie::new(data).unwrap();
+        assert_eq!(lookup(&trie, "usb:v1D6Bp0003"), None);
+    }
+}
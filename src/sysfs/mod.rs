@@ -0,0 +1,179 @@
+// Pure-Rust implementation of the parts of the libudev API that are used by this crate. It's
+// selected by the `sysfs` feature and replaces `libudev-sys` as the `ffi` module, so the rest of
+// the crate works the same with either backend. Devices are read from `/sys` and udev's database
+// in `/run/udev/data`.
+//
+// The functions follow the conventions of libudev: objects are reference-counted and allocated on
+// the heap, strings are returned as pointers into the objects that own them, functions that return
+// pointers set `errno` and return `NULL` on failure, and functions that return `c_int` return a
+// negative `errno` value on failure.
+
+#![allow(non_camel_case_types)]
+
+use std::cell::Cell;
+use std::ffi::{CStr, CString, OsStr};
+use std::ptr;
+
+use std::os::unix::ffi::OsStrExt;
+
+use libc::{c_char, c_int};
+
+pub use self::device::*;
+pub use self::enumerate::*;
+pub use self::hwdb::*;
+pub use self::monitor::*;
+pub use self::queue::*;
+
+mod device;
+mod enumerate;
+mod hwdb;
+mod monitor;
+mod queue;
+
+const SYS_PATH: &str = "/sys";
+const DEV_PATH: &str = "/dev";
+
+
+pub struct udev {
+    refcount: Cell<usize>,
+}
+
+pub unsafe fn udev_new() -> *mut udev {
+    Box::into_raw(Box::new(udev {
+        refcount: Cell::new(1),
+    }))
+}
+
+pub unsafe fn udev_ref(udev: *mut udev) -> *mut udev {
+    if !udev.is_null() {
+        (*udev).refcount.set((*udev).refcount.get() + 1);
+    }
+
+    udev
+}
+
+pub unsafe fn udev_unref(udev: *mut udev) -> *mut udev {
+    if !udev.is_null() {
+        let refcount = (*udev).refcount.get() - 1;
+        (*udev).refcount.set(refcount);
+
+        if refcount == 0 {
+            drop(Box::from_raw(udev));
+        }
+    }
+
+    ptr::null_mut()
+}
+
+
+pub struct udev_list_entry {
+    name: CString,
+    value: Option<CString>,
+    next: *mut udev_list_entry,
+}
+
+pub unsafe fn udev_list_entry_get_next(list_entry: *mut udev_list_entry) -> *mut udev_list_entry {
+    if !list_entry.is_null() {
+        (*list_entry).next
+    }
+    else {
+        ptr::null_mut()
+    }
+}
+
+pub unsafe fn udev_list_entry_get_name(list_entry: *mut udev_list_entry) -> *const c_char {
+    if !list_entry.is_null() {
+        (*list_entry).name.as_ptr()
+    }
+    else {
+        ptr::null()
+    }
+}
+
+pub unsafe fn udev_list_entry_get_value(list_entry: *mut udev_list_entry) -> *const c_char {
+    if !list_entry.is_null() {
+        option_as_ptr(&(*list_entry).value)
+    }
+    else {
+        ptr::null()
+    }
+}
+
+/// The entries of a list that's returned by one of the `*_list_entry` functions.
+///
+/// The entries are never moved after the list is created, so pointers to them stay valid for as
+/// long as the list exists.
+struct List {
+    entries: Vec<udev_list_entry>,
+}
+
+impl List {
+    fn new<I: IntoIterator<Item = (CString, Option<CString>)>>(entries: I) -> List {
+        let mut entries: Vec<udev_list_entry> = entries.into_iter().map(|(name, value)| {
+            udev_list_entry {
+                name: name,
+                value: value,
+                next: ptr::null_mut(),
+            }
+        }).collect();
+
+        for i in 1..entries.len() {
+            let next: *mut udev_list_entry = &mut entries[i];
+            entries[i - 1].next = next;
+        }
+
+        List { entries: entries }
+    }
+
+    fn head(&self) -> *mut udev_list_entry {
+        match self.entries.first() {
+            Some(entry) => entry as *const udev_list_entry as *mut udev_list_entry,
+            None => ptr::null_mut(),
+        }
+    }
+
+    fn iter(&self) -> ::std::slice::Iter<'_, udev_list_entry> {
+        self.entries.iter()
+    }
+
+    fn find(&self, name: &CStr) -> Option<&udev_list_entry> {
+        self.entries.iter().find(|entry| entry.name.as_c_str() == name)
+    }
+}
+
+
+fn set_errno(errno: c_int) {
+    unsafe {
+        *::libc::__errno_location() = errno;
+    }
+}
+
+fn option_as_ptr(s: &Option<CString>) -> *const c_char {
+    match *s {
+        Some(ref s) => s.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+unsafe fn ptr_to_os_str<'a>(ptr: *const c_char) -> Option<&'a OsStr> {
+    if !ptr.is_null() {
+        Some(OsStr::from_bytes(CStr::from_ptr(ptr).to_bytes()))
+    }
+    else {
+        None
+    }
+}
+
+/// Converts bytes to a `CString`, truncating them at the first nul byte.
+fn to_cstring<T: AsRef<[u8]>>(bytes: T) -> CString {
+    let bytes = bytes.as_ref();
+
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+
+    CString::new(&bytes[..len]).unwrap_or_default()
+}
+
+/// Matches a string against a shell glob pattern.
+fn fnmatch(pattern: &CStr, s: &CStr) -> bool {
+    unsafe { ::libc::fnmatch(pattern.as_ptr(), s.as_ptr(), 0) == 0 }
+}
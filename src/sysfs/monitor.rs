@@ -0,0 +1,546 @@
+use std::cell::Cell;
+use std::ffi::CString;
+use std::mem;
+use std::ptr;
+
+use std::os::unix::ffi::OsStrExt;
+
+use libc::{c_char, c_int, c_void, EINVAL};
+
+use super::device::device_new;
+use super::{ptr_to_os_str, set_errno, to_cstring, udev, udev_device};
+
+
+// Events are received from a `NETLINK_KOBJECT_UEVENT` socket. The kernel sends its uevents to the
+// first multicast group, and udevd forwards processed events to the second group in its own
+// format. Unlike libudev, which loads the filters into the kernel as a BPF program, filters are
+// applied in-process when events are received.
+
+const GROUP_KERNEL: u32 = 1;
+const GROUP_UDEV: u32 = 2;
+
+const BUFFER_SIZE: usize = 8192;
+
+const UDEV_PREFIX: &[u8] = b"libudev\0";
+const UDEV_MAGIC: u32 = 0xfeedcafe;
+const UDEV_HEADER_SIZE: usize = 40;
+
+pub struct udev_monitor {
+    refcount: Cell<usize>,
+    udev: *mut udev,
+    fd: c_int,
+    group: u32,
+    subsystem_filters: Vec<(CString, Option<CString>)>,
+    tag_filters: Vec<CString>,
+}
+
+impl udev_monitor {
+    /// Returns whether a message passes the monitor's filters.
+    fn passes_filters(&self, message: &Message) -> bool {
+        if let Some(ref hashes) = message.hashes {
+            if !self.subsystem_filters.is_empty() {
+                let matches = self.subsystem_filters.iter().any(|&(ref subsystem, ref devtype)| {
+                    string_hash32(subsystem.as_bytes()) == hashes.subsystem
+                        && devtype.as_ref().is_none_or(|devtype| string_hash32(devtype.as_bytes()) == hashes.devtype)
+                });
+
+                if !matches {
+                    return false;
+                }
+            }
+
+            if !self.tag_filters.is_empty() {
+                let matches = self.tag_filters.iter().any(|tag| {
+                    let bits = string_bloom64(tag.as_bytes());
+                    hashes.tag_bloom & bits == bits
+                });
+
+                if !matches {
+                    return false;
+                }
+            }
+        }
+
+        // The hashes can have collisions, so the properties are checked even when they match.
+        if !self.subsystem_filters.is_empty() {
+            let subsystem = message.property(b"SUBSYSTEM");
+            let devtype = message.property(b"DEVTYPE");
+
+            let matches = self.subsystem_filters.iter().any(|&(ref s, ref d)| {
+                subsystem == Some(s.as_bytes()) && d.as_ref().is_none_or(|d| devtype == Some(d.as_bytes()))
+            });
+
+            if !matches {
+                return false;
+            }
+        }
+
+        if !self.tag_filters.is_empty() {
+            let tags = message.property(b"TAGS").unwrap_or(b"");
+
+            let matches = self.tag_filters.iter().any(|tag| {
+                tags.split(|&b| b == b':').any(|t| t == tag.as_bytes())
+            });
+
+            if !matches {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Drop for udev_monitor {
+    fn drop(&mut self) {
+        unsafe {
+            ::libc::close(self.fd);
+        }
+    }
+}
+
+/// The filter hashes from the header of a message that was sent by udevd.
+struct Hashes {
+    subsystem: u32,
+    devtype: u32,
+    tag_bloom: u64,
+}
+
+/// A uevent message that was received from the netlink socket.
+struct Message {
+    properties: Vec<(Vec<u8>, Vec<u8>)>,
+    hashes: Option<Hashes>,
+}
+
+impl Message {
+    /// Parses a message that was sent by the kernel or by udevd.
+    ///
+    /// Kernel messages start with a summary of the form `ACTION@DEVPATH`. Messages from udevd start
+    /// with a header that contains the location of the properties and hashes of the values that
+    /// are used for filtering. In both formats, the properties are `KEY=VALUE` strings that are
+    /// separated by nul bytes. Like systemd, messages on the udev group are only accepted with the
+    /// udevd header.
+    fn parse(buf: &[u8], group: u32) -> Option<Message> {
+        let (properties, hashes) = if buf.starts_with(UDEV_PREFIX) {
+            if buf.len() < UDEV_HEADER_SIZE || read_u32_be(buf, 8) != UDEV_MAGIC {
+                return None;
+            }
+
+            let header_size = read_u32_ne(buf, 12) as usize;
+            let offset = read_u32_ne(buf, 16) as usize;
+            let len = read_u32_ne(buf, 20) as usize;
+
+            if header_size < UDEV_HEADER_SIZE || offset < header_size || offset.checked_add(len).is_none_or(|end| end > buf.len()) {
+                return None;
+            }
+
+            let hashes = Hashes {
+                subsystem: read_u32_be(buf, 24),
+                devtype: read_u32_be(buf, 28),
+                tag_bloom: (read_u32_be(buf, 32) as u64) << 32 | read_u32_be(buf, 36) as u64,
+            };
+
+            (&buf[offset..offset + len], Some(hashes))
+        }
+        else {
+            if group == GROUP_UDEV {
+                return None;
+            }
+
+            let summary_len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+
+            if !buf[..summary_len].contains(&b'@') {
+                return None;
+            }
+
+            (&buf[(summary_len + 1).min(buf.len())..], None)
+        };
+
+        let properties = properties.split(|&b| b == 0).filter_map(|property| {
+            property.iter().position(|&b| b == b'=').map(|i| (property[..i].to_vec(), property[i + 1..].to_vec()))
+        }).collect();
+
+        Some(Message {
+            properties: properties,
+            hashes: hashes,
+        })
+    }
+
+    fn property(&self, key: &[u8]) -> Option<&[u8]> {
+        self.properties.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref value)| &value[..])
+    }
+}
+
+fn read_u32_ne(buf: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn read_u32_be(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+/// Computes the MurmurHash2 of a string with a seed of 0, like udevd does for filter hashes.
+fn string_hash32(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1e995;
+
+    let mut h = data.len() as u32;
+
+    let mut chunks = data.chunks_exact(4);
+
+    for chunk in &mut chunks {
+        let mut k = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+
+    let tail = chunks.remainder();
+
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u32) << (8 * i);
+        }
+
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+
+    h
+}
+
+/// Returns the bits that a string sets in the bloom filter of the tags of a device.
+fn string_bloom64(data: &[u8]) -> u64 {
+    let hash = string_hash32(data);
+
+    (0..4).fold(0, |bits, i| bits | 1 << ((hash >> (6 * i)) & 63))
+}
+
+/// Receives the next message from a socket.
+///
+/// Returns `Ok(None)` for messages that should be ignored, because they were truncated or weren't
+/// sent by a trusted sender.
+unsafe fn receive_message(monitor: &udev_monitor, buf: &mut [u8]) -> Result<Option<usize>, c_int> {
+    let mut addr: ::libc::sockaddr_nl = mem::zeroed();
+
+    let mut iov = ::libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+
+    let mut control = vec![0u8; ::libc::CMSG_SPACE(mem::size_of::<::libc::ucred>() as u32) as usize];
+
+    let mut msg: ::libc::msghdr = mem::zeroed();
+    msg.msg_name = &mut addr as *mut ::libc::sockaddr_nl as *mut c_void;
+    msg.msg_namelen = mem::size_of::<::libc::sockaddr_nl>() as ::libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = control.len() as _;
+
+    let len = ::libc::recvmsg(monitor.fd, &mut msg, 0);
+
+    if len < 0 {
+        return Err(*::libc::__errno_location());
+    }
+
+    if msg.msg_flags & ::libc::MSG_TRUNC != 0 {
+        return Ok(None);
+    }
+
+    // Kernel events must be sent by the kernel, and all events must be sent by root.
+    if monitor.group == GROUP_KERNEL && addr.nl_pid != 0 {
+        return Ok(None);
+    }
+
+    let cmsg = ::libc::CMSG_FIRSTHDR(&msg);
+
+    if cmsg.is_null() || (*cmsg).cmsg_level != ::libc::SOL_SOCKET || (*cmsg).cmsg_type != ::libc::SCM_CREDENTIALS {
+        return Ok(None);
+    }
+
+    let credentials = ptr::read_unaligned(::libc::CMSG_DATA(cmsg) as *const ::libc::ucred);
+
+    if credentials.uid != 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(len as usize))
+}
+
+unsafe fn set_socket_option(fd: c_int, option: c_int, value: c_int) -> c_int {
+    let result = ::libc::setsockopt(fd, ::libc::SOL_SOCKET, option, &value as *const c_int as *const c_void, mem::size_of::<c_int>() as ::libc::socklen_t);
+
+    if result < 0 {
+        -*::libc::__errno_location()
+    }
+    else {
+        0
+    }
+}
+
+
+pub unsafe fn udev_monitor_unref(udev_monitor: *mut udev_monitor) -> *mut udev_monitor {
+    if !udev_monitor.is_null() {
+        let refcount = (*udev_monitor).refcount.get() - 1;
+        (*udev_monitor).refcount.set(refcount);
+
+        if refcount == 0 {
+            drop(Box::from_raw(udev_monitor));
+        }
+    }
+
+    ptr::null_mut()
+}
+
+pub unsafe fn udev_monitor_get_udev(udev_monitor: *mut udev_monitor) -> *mut udev {
+    (*udev_monitor).udev
+}
+
+pub unsafe fn udev_monitor_new_from_netlink(udev: *mut udev, name: *const c_char) -> *mut udev_monitor {
+    let group = match ptr_to_os_str(name).map(|name| name.as_bytes()) {
+        Some(b"udev") => GROUP_UDEV,
+        Some(b"kernel") => GROUP_KERNEL,
+        _ => {
+            set_errno(EINVAL);
+            return ptr::null_mut();
+        },
+    };
+
+    let fd = ::libc::socket(::libc::AF_NETLINK, ::libc::SOCK_RAW | ::libc::SOCK_CLOEXEC | ::libc::SOCK_NONBLOCK, ::libc::NETLINK_KOBJECT_UEVENT);
+
+    if fd < 0 {
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(udev_monitor {
+        refcount: Cell::new(1),
+        udev: udev,
+        fd: fd,
+        group: group,
+        subsystem_filters: Vec::new(),
+        tag_filters: Vec::new(),
+    }))
+}
+
+pub unsafe fn udev_monitor_enable_receiving(udev_monitor: *mut udev_monitor) -> c_int {
+    let monitor = &*udev_monitor;
+
+    let mut addr: ::libc::sockaddr_nl = mem::zeroed();
+    addr.nl_family = ::libc::AF_NETLINK as ::libc::sa_family_t;
+    addr.nl_groups = monitor.group;
+
+    let result = ::libc::bind(monitor.fd, &addr as *const ::libc::sockaddr_nl as *const ::libc::sockaddr, mem::size_of::<::libc::sockaddr_nl>() as ::libc::socklen_t);
+
+    if result < 0 {
+        return -*::libc::__errno_location();
+    }
+
+    set_socket_option(monitor.fd, ::libc::SO_PASSCRED, 1)
+}
+
+pub unsafe fn udev_monitor_set_receive_buffer_size(udev_monitor: *mut udev_monitor, size: c_int) -> c_int {
+    let fd = (*udev_monitor).fd;
+
+    // Forcing the size requires `CAP_NET_ADMIN`. Without it, the size is limited by `rmem_max`.
+    match set_socket_option(fd, ::libc::SO_RCVBUFFORCE, size) {
+        result if result == -::libc::EPERM => set_socket_option(fd, ::libc::SO_RCVBUF, size),
+        result => result,
+    }
+}
+
+pub unsafe fn udev_monitor_get_fd(udev_monitor: *mut udev_monitor) -> c_int {
+    (*udev_monitor).fd
+}
+
+pub unsafe fn udev_monitor_receive_device(udev_monitor: *mut udev_monitor) -> *mut udev_device {
+    let monitor = &*udev_monitor;
+    let mut buf = [0u8; BUFFER_SIZE];
+
+    loop {
+        let len = match receive_message(monitor, &mut buf) {
+            Ok(Some(len)) => len,
+            Ok(None) => continue,
+            Err(errno) => {
+                set_errno(errno);
+                return ptr::null_mut();
+            },
+        };
+
+        let message = match Message::parse(&buf[..len], monitor.group) {
+            Some(message) => message,
+            None => continue,
+        };
+
+        let complete = [&b"ACTION"[..], b"DEVPATH", b"SUBSYSTEM", b"SEQNUM"].iter().all(|key| message.property(key).is_some());
+
+        if !complete || !monitor.passes_filters(&message) {
+            continue;
+        }
+
+        if let Some(device) = device_new(monitor.udev, message.properties, false) {
+            return device;
+        }
+    }
+}
+
+pub unsafe fn udev_monitor_filter_add_match_subsystem_devtype(udev_monitor: *mut udev_monitor, subsystem: *const c_char, devtype: *const c_char) -> c_int {
+    match ptr_to_os_str(subsystem) {
+        Some(subsystem) => {
+            let devtype = ptr_to_os_str(devtype).map(|devtype| to_cstring(devtype.as_bytes()));
+            (*udev_monitor).subsystem_filters.push((to_cstring(subsystem.as_bytes()), devtype));
+            0
+        },
+        None => -EINVAL,
+    }
+}
+
+pub unsafe fn udev_monitor_filter_add_match_tag(udev_monitor: *mut udev_monitor, tag: *const c_char) -> c_int {
+    match ptr_to_os_str(tag) {
+        Some(tag) => {
+            (*udev_monitor).tag_filters.push(to_cstring(tag.as_bytes()));
+            0
+        },
+        None => -EINVAL,
+    }
+}
+
+pub unsafe fn udev_monitor_filter_update(_udev_monitor: *mut udev_monitor) -> c_int {
+    // Filters are applied when events are received, so there's nothing to update.
+    0
+}
+
+pub unsafe fn udev_monitor_filter_remove(udev_monitor: *mut udev_monitor) -> c_int {
+    (*udev_monitor).subsystem_filters.clear();
+    (*udev_monitor).tag_filters.clear();
+
+    0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{string_bloom64, string_hash32, Message, GROUP_KERNEL, GROUP_UDEV, UDEV_HEADER_SIZE, UDEV_MAGIC, UDEV_PREFIX};
+
+    const PROPERTIES: &[u8] = b"ACTION=add\0DEVPATH=/devices/virtual/block/loop0\0SUBSYSTEM=block\0DEVTYPE=disk\0SEQNUM=42\0";
+
+    /// Builds a message like udevd sends it, with the properties after the header.
+    fn udev_message(properties: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(UDEV_PREFIX);
+        buf.extend_from_slice(&UDEV_MAGIC.to_be_bytes());
+        buf.extend_from_slice(&(UDEV_HEADER_SIZE as u32).to_ne_bytes());
+        buf.extend_from_slice(&(UDEV_HEADER_SIZE as u32).to_ne_bytes());
+        buf.extend_from_slice(&(properties.len() as u32).to_ne_bytes());
+        buf.extend_from_slice(&string_hash32(b"block").to_be_bytes());
+        buf.extend_from_slice(&string_hash32(b"disk").to_be_bytes());
+        buf.extend_from_slice(&((string_bloom64(b"systemd") >> 32) as u32).to_be_bytes());
+        buf.extend_from_slice(&(string_bloom64(b"systemd") as u32).to_be_bytes());
+        buf.extend_from_slice(properties);
+
+        buf
+    }
+
+    #[test]
+    fn parse_kernel_message() {
+        let mut buf = b"add@/devices/virtual/block/loop0\0".to_vec();
+        buf.extend_from_slice(PROPERTIES);
+
+        let message = Message::parse(&buf, GROUP_KERNEL).unwrap();
+
+        assert!(message.hashes.is_none());
+        assert_eq!(message.properties.len(), 5);
+        assert_eq!(message.property(b"ACTION"), Some(&b"add"[..]));
+        assert_eq!(message.property(b"DEVPATH"), Some(&b"/devices/virtual/block/loop0"[..]));
+        assert_eq!(message.property(b"SEQNUM"), Some(&b"42"[..]));
+        assert_eq!(message.property(b"DEVNAME"), None);
+    }
+
+    #[test]
+    fn parse_kernel_message_without_summary() {
+        assert!(Message::parse(PROPERTIES, GROUP_KERNEL).is_none());
+    }
+
+    #[test]
+    fn parse_kernel_message_on_udev_group() {
+        let mut buf = b"add@/devices/virtual/block/loop0\0".to_vec();
+        buf.extend_from_slice(PROPERTIES);
+
+        assert!(Message::parse(&buf, GROUP_UDEV).is_none());
+    }
+
+    #[test]
+    fn parse_udev_message() {
+        let message = Message::parse(&udev_message(PROPERTIES), GROUP_UDEV).unwrap();
+        let hashes = message.hashes.as_ref().unwrap();
+
+        assert_eq!(hashes.subsystem, string_hash32(b"block"));
+        assert_eq!(hashes.devtype, string_hash32(b"disk"));
+        assert_eq!(hashes.tag_bloom, string_bloom64(b"systemd"));
+
+        assert_eq!(message.properties.len(), 5);
+        assert_eq!(message.property(b"SUBSYSTEM"), Some(&b"block"[..]));
+        assert_eq!(message.property(b"DEVTYPE"), Some(&b"disk"[..]));
+    }
+
+    #[test]
+    fn parse_udev_message_with_bad_magic() {
+        let mut buf = udev_message(PROPERTIES);
+        buf[8] ^= 0xff;
+
+        assert!(Message::parse(&buf, GROUP_UDEV).is_none());
+    }
+
+    #[test]
+    fn parse_truncated_udev_header() {
+        let buf = udev_message(PROPERTIES);
+
+        assert!(Message::parse(&buf[..UDEV_HEADER_SIZE - 1], GROUP_UDEV).is_none());
+        assert!(Message::parse(UDEV_PREFIX, GROUP_UDEV).is_none());
+    }
+
+    #[test]
+    fn parse_udev_message_with_bad_properties_offset() {
+        let mut buf = udev_message(PROPERTIES);
+        let len = buf.len();
+
+        // Properties that start inside the header.
+        buf[16..20].copy_from_slice(&(UDEV_HEADER_SIZE as u32 - 1).to_ne_bytes());
+        assert!(Message::parse(&buf, GROUP_UDEV).is_none());
+
+        // Properties that end after the message.
+        buf[16..20].copy_from_slice(&(UDEV_HEADER_SIZE as u32 + 1).to_ne_bytes());
+        assert!(Message::parse(&buf, GROUP_UDEV).is_none());
+
+        // An offset that overflows when the length is added to it.
+        buf[16..20].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert!(Message::parse(&buf, GROUP_UDEV).is_none());
+
+        buf[16..20].copy_from_slice(&(UDEV_HEADER_SIZE as u32).to_ne_bytes());
+        assert!(Message::parse(&buf[..len - 1], GROUP_UDEV).is_none());
+    }
+
+    #[test]
+    fn string_hash32_matches_systemd() {
+        // Values computed with the MurmurHash2 implementation from systemd.
+        assert_eq!(string_hash32(b""), 0x00000000);
+        assert_eq!(string_hash32(b"a"), 0x92685f5e);
+        assert_eq!(string_hash32(b"block"), 0xf0031db7);
+        assert_eq!(string_hash32(b"disk"), 0x7bcbc5ee);
+        assert_eq!(string_hash32(b"usb_device"), 0x27f8f50c);
+    }
+
+    #[test]
+    fn string_bloom64_matches_systemd() {
+        assert_eq!(string_bloom64(b""), 0x0000000000000001);
+        assert_eq!(string_bloom64(b"seat"), 0x0208000000400001);
+        assert_eq!(string_bloom64(b"systemd"), 0x0200040010800000);
+        assert_eq!(string_bloom64(b"uaccess"), 0x0000200800001008);
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::Cell;
+use std::ffi::CString;
+use std::io;
+use std::path::Path;
+use std::ptr;
+
+use libc::{c_int, EINVAL};
+
+use super::{set_errno, udev};
+
+
+// The udev daemon creates `/run/udev/control` while it's running, and `/run/udev/queue` while it
+// has events to process. Changes of the queue are reported by watching `/run/udev` for deleted
+// files with inotify.
+
+const RUN_PATH: &str = "/run/udev";
+
+pub struct udev_queue {
+    refcount: Cell<usize>,
+    fd: Cell<c_int>,
+}
+
+impl Drop for udev_queue {
+    fn drop(&mut self) {
+        if self.fd.get() >= 0 {
+            unsafe {
+                ::libc::close(self.fd.get());
+            }
+        }
+    }
+}
+
+pub unsafe fn udev_queue_new(udev: *mut udev) -> *mut udev_queue {
+    if udev.is_null() {
+        set_errno(EINVAL);
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(udev_queue {
+        refcount: Cell::new(1),
+        fd: Cell::new(-1),
+    }))
+}
+
+pub unsafe fn udev_queue_unref(udev_queue: *mut udev_queue) -> *mut udev_queue {
+    if !udev_queue.is_null() {
+        let refcount = (*udev_queue).refcount.get() - 1;
+        (*udev_queue).refcount.set(refcount);
+
+        if refcount == 0 {
+            drop(Box::from_raw(udev_queue));
+        }
+    }
+
+    ptr::null_mut()
+}
+
+pub unsafe fn udev_queue_get_udev_is_active(_udev_queue: *mut udev_queue) -> c_int {
+    Path::new(RUN_PATH).join("control").exists() as c_int
+}
+
+pub unsafe fn udev_queue_get_queue_is_empty(_udev_queue: *mut udev_queue) -> c_int {
+    match Path::new(RUN_PATH).join("queue").metadata() {
+        Ok(_) => 0,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => 1,
+        Err(err) => -err.raw_os_error().unwrap_or(EINVAL),
+    }
+}
+
+pub unsafe fn udev_queue_get_fd(udev_queue: *mut udev_queue) -> c_int {
+    if udev_queue.is_null() {
+        return -EINVAL;
+    }
+
+    let queue = &*udev_queue;
+
+    if queue.fd.get() >= 0 {
+        return queue.fd.get();
+    }
+
+    let fd = ::libc::inotify_init1(::libc::IN_CLOEXEC);
+
+    if fd < 0 {
+        return -io::Error::last_os_error().raw_os_error().unwrap_or(EINVAL);
+    }
+
+    let path = CString::new(RUN_PATH).unwrap();
+
+    if ::libc::inotify_add_watch(fd, path.as_ptr(), ::libc::IN_DELETE) < 0 {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(EINVAL);
+        ::libc::close(fd);
+        return -errno;
+    }
+
+    queue.fd.set(fd);
+    fd
+}
+
+pub unsafe fn udev_queue_flush(udev_queue: *mut udev_queue) -> c_int {
+    if udev_queue.is_null() || (*udev_queue).fd.get() < 0 {
+        return -EINVAL;
+    }
+
+    let fd = (*udev_queue).fd.get();
+    let mut buf = [0u8; 4096];
+
+    loop {
+        let mut pollfd = ::libc::pollfd { fd: fd, events: ::libc::POLLIN, revents: 0 };
+
+        let ready = ::libc::poll(&mut pollfd, 1, 0);
+
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+
+            return -err.raw_os_error().unwrap_or(EINVAL);
+        }
+
+        if ready == 0 {
+            return 0;
+        }
+
+        if ::libc::read(fd, buf.as_mut_ptr() as *mut _, buf.len()) < 0 {
+            let err = io::Error::last_os_error();
+
+            if err.kind() != io::ErrorKind::Interrupted {
+                return -err.raw_os_error().unwrap_or(EINVAL);
+            }
+        }
+    }
+}
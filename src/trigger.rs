@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use ::device::Device;
+use ::enumerator::Enumerator;
+use ::monitor::{EventType, MonitorSocket};
+
+
+/// A synthetic uevent that can be triggered for devices, like `udevadm trigger`.
+///
+/// Triggering an event writes its action to the device's `uevent` attribute, which makes the
+/// kernel emit a uevent for the device, so that udev runs its rules for the device again. An event
+/// can carry a UUID, which the kernel adds to the event as the `SYNTH_UUID` property, and
+/// arguments, which are added as `SYNTH_ARG_` properties. Arguments are only accepted together
+/// with a UUID.
+///
+/// The UUID identifies the triggered events, so that `wait()` can wait until udev has processed
+/// them. The monitor socket must be listening before the events are triggered.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// let context = libudev::Context::new().unwrap();
+/// let mut socket = libudev::Monitor::new(&context).unwrap().listen().unwrap();
+///
+/// let mut enumerator = libudev::Enumerator::new(&context).unwrap();
+/// enumerator.match_subsystem("block").unwrap();
+///
+/// let trigger = libudev::Trigger::new(libudev::EventType::Change).unwrap().random_uuid().unwrap();
+/// let syspaths = trigger.trigger_all(&mut enumerator).unwrap();
+///
+/// trigger.wait(&mut socket, syspaths, Duration::from_secs(30)).unwrap();
+/// ```
+#[derive(Debug,Clone)]
+pub struct Trigger {
+    action: EventType,
+    uuid: Option<String>,
+    args: Vec<(OsString, OsString)>,
+}
+
+impl Trigger {
+    /// Creates a trigger for events of the given type.
+    ///
+    /// The kernel accepts the actions `add`, `remove`, `change`, `move`, `online`, `offline`,
+    /// `bind`, and `unbind`. `EventType::Other` is accepted if it names one of those actions, e.g.,
+    /// `EventType::Other("add")` is treated like `EventType::Add`. Returns an error of kind
+    /// `ErrorKind::InvalidInput` for other actions and for `EventType::Unknown`.
+    pub fn new(action: EventType) -> ::Result<Self> {
+        let action = match action {
+            EventType::Other(ref other) => ::monitor::event_type_from_action(OsStr::new(other)),
+            action => action,
+        };
+
+        match action {
+            EventType::Other(_) | EventType::Unknown => {
+                Err(::error::with_message(::libc::EINVAL, format!("can't trigger events with the action {:?}", action.to_string())))
+            },
+            action => {
+                Ok(Trigger {
+                    action: action,
+                    uuid: None,
+                    args: Vec::new(),
+                })
+            },
+        }
+    }
+
+    /// Sets the UUID of the events.
+    pub fn uuid<T: Into<String>>(mut self, uuid: T) -> Self {
+        self.uuid = Some(uuid.into());
+        self
+    }
+
+    /// Sets the UUID of the events to a new random UUID.
+    pub fn random_uuid(mut self) -> ::Result<Self> {
+        let mut bytes = [0u8; 16];
+        try!(::util::fill_random(&mut bytes));
+
+        // Version 4, variant 1.
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        self.uuid = Some(format!("{}-{}-{}-{}-{}",
+                                 hex[0..4].concat(), hex[4..6].concat(), hex[6..8].concat(), hex[8..10].concat(), hex[10..16].concat()));
+
+        Ok(self)
+    }
+
+    /// Adds an argument to the events. The kernel adds it to the events as the property
+    /// `SYNTH_ARG_<key>`.
+    pub fn arg<T: AsRef<OsStr>, U: AsRef<OsStr>>(mut self, key: T, value: U) -> Self {
+        self.args.push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    /// Returns the type of the events.
+    pub fn action(&self) -> &EventType {
+        &self.action
+    }
+
+    /// Returns the UUID of the events, if any.
+    pub fn synth_uuid(&self) -> Option<&str> {
+        self.uuid.as_ref().map(|uuid| &uuid[..])
+    }
+
+    /// Triggers an event for a device.
+    pub fn trigger(&self, device: &mut Device) -> ::Result<()> {
+        let value = try!(self.uevent_value());
+
+        device.set_attribute_value("uevent", value)
+    }
+
+    /// Triggers an event for every device that matches the enumerator's filters.
+    ///
+    /// Returns the syspaths of the devices for which an event was triggered. Devices that are
+    /// removed before their event is triggered are skipped, like `udevadm trigger` skips them. Any
+    /// other error stops triggering events.
+    pub fn trigger_all(&self, enumerator: &mut Enumerator) -> ::Result<Vec<PathBuf>> {
+        let value = try!(self.uevent_value());
+        let mut syspaths = Vec::new();
+
+        for mut device in try!(enumerator.scan_devices()) {
+            match device.set_attribute_value("uevent", &value) {
+                Ok(()) => (),
+                Err(ref err) if is_device_absent(err) => continue,
+                Err(err) => return Err(err),
+            }
+
+            if let Some(syspath) = device.syspath() {
+                syspaths.push(syspath.to_path_buf());
+            }
+        }
+
+        Ok(syspaths)
+    }
+
+    /// Blocks until the socket has received the triggered events of all the given devices or the
+    /// timeout expires.
+    ///
+    /// Events are matched by their `SYNTH_UUID` property and syspath, so the trigger must have a
+    /// UUID. Other events that are received in the meantime are discarded. If the timeout expires,
+    /// this method returns an error of kind `ErrorKind::Io(io::ErrorKind::TimedOut)`. An overflow of
+    /// the socket is returned as an error of kind `ErrorKind::Overflow`, because the triggered
+    /// events may have been lost.
+    pub fn wait<I: IntoIterator<Item = P>, P: AsRef<Path>>(&self, socket: &mut MonitorSocket, syspaths: I, timeout: Duration) -> ::Result<()> {
+        let uuid = match self.uuid {
+            Some(ref uuid) => OsStr::new(uuid),
+            None => return Err(::error::with_message(::libc::EINVAL, String::from("waiting for triggered events requires a UUID"))),
+        };
+
+        let deadline = Instant::now() + timeout;
+        let mut pending: HashSet<PathBuf> = syspaths.into_iter().map(|syspath| syspath.as_ref().to_path_buf()).collect();
+
+        while !pending.is_empty() {
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Err(::error::from_raw_os_error(::libc::ETIMEDOUT));
+            }
+
+            let event = try!(socket.recv_timeout(deadline - now));
+
+            if event.property_value("SYNTH_UUID") == Some(uuid) {
+                if let Some(syspath) = event.syspath() {
+                    pending.remove(syspath);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the value that's written to the `uevent` attribute.
+    fn uevent_value(&self) -> ::Result<OsString> {
+        let mut value = OsString::from(self.action.to_string());
+
+        match self.uuid {
+            Some(ref uuid) => {
+                value.push(" ");
+                value.push(uuid);
+            },
+            None => {
+                if !self.args.is_empty() {
+                    return Err(::error::with_message(::libc::EINVAL, String::from("synthetic uevent arguments require a UUID")));
+                }
+            },
+        }
+
+        for &(ref key, ref arg) in &self.args {
+            value.push(" ");
+            value.push(key);
+            value.push("=");
+            value.push(arg);
+        }
+
+        Ok(value)
+    }
+}
+
+/// Checks whether an error means that the device went away, like `ERRNO_IS_DEVICE_ABSENT()` in
+/// udev.
+fn is_device_absent(err: &::Error) -> bool {
+    matches!(::error::raw_os_error(err), ::libc::ENOENT | ::libc::ENODEV | ::libc::ENXIO)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use ::monitor::EventType;
+
+    use super::{is_device_absent, Trigger};
+
+    #[test]
+    fn new_rejects_actions_the_kernel_does_not_accept() {
+        assert_eq!(Trigger::new(EventType::Unknown).unwrap_err().kind(), ::ErrorKind::InvalidInput);
+        assert_eq!(Trigger::new(EventType::Other(String::from("adding"))).unwrap_err().kind(), ::ErrorKind::InvalidInput);
+        assert_eq!(Trigger::new(EventType::Other(String::from(""))).unwrap_err().kind(), ::ErrorKind::InvalidInput);
+        assert_eq!(Trigger::new(EventType::Offline).unwrap().action(), &EventType::Offline);
+    }
+
+    #[test]
+    fn new_normalizes_other_actions() {
+        assert_eq!(Trigger::new(EventType::Other(String::from("add"))).unwrap().action(), &EventType::Add);
+        assert_eq!(Trigger::new(EventType::Other(String::from("unbind"))).unwrap().action(), &EventType::Unbind);
+    }
+
+    #[test]
+    fn uevent_value() {
+        let trigger = Trigger::new(EventType::Change).unwrap();
+        assert_eq!(trigger.uevent_value().unwrap(), OsString::from("change"));
+
+        let trigger = trigger.uuid("fc73c9b6-2e1d-4b3c-a8d4-3f9b0e6d7a21").arg("ACTION_ID", "1");
+        assert_eq!(trigger.uevent_value().unwrap(), OsString::from("change fc73c9b6-2e1d-4b3c-a8d4-3f9b0e6d7a21 ACTION_ID=1"));
+    }
+
+    #[test]
+    fn uevent_value_with_arguments_requires_uuid() {
+        let trigger = Trigger::new(EventType::Add).unwrap().arg("ACTION_ID", "1");
+
+        assert_eq!(trigger.uevent_value().unwrap_err().kind(), ::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn random_uuid_is_version_4() {
+        let trigger = Trigger::new(EventType::Change).unwrap().random_uuid().unwrap();
+        let uuid = trigger.synth_uuid().unwrap();
+
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!("89ab".contains(&uuid[19..20]));
+    }
+
+    #[test]
+    fn absent_devices() {
+        assert!(is_device_absent(&::error::from_raw_os_error(::libc::ENOENT)));
+        assert!(is_device_absent(&::error::from_errno(-::libc::ENODEV)));
+        assert!(is_device_absent(&::error::from_raw_os_error(::libc::ENXIO)));
+        assert!(!is_device_absent(&::error::from_raw_os_error(::libc::EACCES)));
+    }
+}
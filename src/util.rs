@@ -1,6 +1,10 @@
+use std::cmp;
+use std::io;
+use std::mem;
 use std::slice;
 use std::ffi::{CString, OsStr};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use libc::{c_int, c_char};
 
@@ -44,3 +48,166 @@ pub fn errno_to_result(errno: c_int) -> ::Result<()> {
         e => Err(::error::from_errno(e)),
     }
 }
+
+/// Waits until at least one of the file descriptors is readable or the timeout expires.
+///
+/// Returns whether each file descriptor is readable. All entries are `false` if the timeout
+/// expired. Interrupted system calls are restarted with the remaining timeout. If a file descriptor
+/// is invalid or has a pending error, that error is returned instead, so that callers don't keep
+/// polling a broken file descriptor.
+pub fn poll_readable(fds: &[RawFd], timeout: Option<Duration>) -> ::Result<Vec<bool>> {
+    let mut pollfds: Vec<::libc::pollfd> = fds.iter().map(|&fd| {
+        ::libc::pollfd { fd: fd, events: ::libc::POLLIN, revents: 0 }
+    }).collect();
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        let timeout_ms = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                // Round up, so that poll() doesn't return before the deadline.
+                let mut millis = remaining.as_millis();
+
+                if remaining.subsec_nanos() % 1_000_000 != 0 {
+                    millis += 1;
+                }
+
+                cmp::min(millis, c_int::MAX as u128) as c_int
+            },
+            None => -1,
+        };
+
+        let result = unsafe {
+            ::libc::poll(pollfds.as_mut_ptr(), pollfds.len() as ::libc::nfds_t, timeout_ms)
+        };
+
+        if result >= 0 {
+            break;
+        }
+
+        let err = io::Error::last_os_error();
+
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(::error::from_io_error(err));
+        }
+    }
+
+    for pollfd in &pollfds {
+        if pollfd.revents & ::libc::POLLNVAL != 0 {
+            return Err(::error::from_raw_os_error(::libc::EBADF));
+        }
+
+        if pollfd.revents & ::libc::POLLERR != 0 {
+            return Err(pending_error(pollfd.fd));
+        }
+    }
+
+    Ok(pollfds.iter().map(|pollfd| pollfd.revents & ::libc::POLLIN != 0).collect())
+}
+
+/// Takes the pending error of a socket. Returns `EIO` for file descriptors that aren't sockets.
+fn pending_error(fd: RawFd) -> ::Error {
+    let mut errno: c_int = 0;
+    let mut len = mem::size_of::<c_int>() as ::libc::socklen_t;
+
+    let result = unsafe {
+        ::libc::getsockopt(fd, ::libc::SOL_SOCKET, ::libc::SO_ERROR, &mut errno as *mut c_int as *mut ::libc::c_void, &mut len)
+    };
+
+    if result < 0 || errno == 0 {
+        return ::error::from_raw_os_error(::libc::EIO);
+    }
+
+    ::error::from_raw_os_error(errno)
+}
+
+/// Fills the buffer with random bytes from the kernel's random number generator.
+pub fn fill_random(buf: &mut [u8]) -> ::Result<()> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        let result = unsafe {
+            ::libc::getrandom(buf[filled..].as_mut_ptr() as *mut ::libc::c_void, buf.len() - filled, 0)
+        };
+
+        if result < 0 {
+            let err = io::Error::last_os_error();
+
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+
+            return Err(::error::from_io_error(err));
+        }
+
+        filled += result as usize;
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::RawFd;
+    use std::time::{Duration, Instant};
+
+    use super::poll_readable;
+
+    struct Pipe {
+        read: RawFd,
+        write: RawFd,
+    }
+
+    impl Pipe {
+        fn new() -> Pipe {
+            let mut fds = [0; 2];
+            assert_eq!(unsafe { ::libc::pipe2(fds.as_mut_ptr(), ::libc::O_CLOEXEC | ::libc::O_NONBLOCK) }, 0);
+
+            Pipe { read: fds[0], write: fds[1] }
+        }
+
+        fn write(&self) {
+            assert_eq!(unsafe { ::libc::write(self.write, b"x".as_ptr() as *const _, 1) }, 1);
+        }
+    }
+
+    impl Drop for Pipe {
+        fn drop(&mut self) {
+            unsafe {
+                ::libc::close(self.read);
+                ::libc::close(self.write);
+            }
+        }
+    }
+
+    #[test]
+    fn poll_readable_reports_readable_fds() {
+        let (first, second) = (Pipe::new(), Pipe::new());
+
+        assert_eq!(poll_readable(&[first.read, second.read], Some(Duration::from_secs(0))).unwrap(), vec![false, false]);
+
+        second.write();
+        assert_eq!(poll_readable(&[first.read, second.read], None).unwrap(), vec![false, true]);
+    }
+
+    #[test]
+    fn poll_readable_waits_for_timeout() {
+        let pipe = Pipe::new();
+        let start = Instant::now();
+
+        assert_eq!(poll_readable(&[pipe.read], Some(Duration::from_millis(50))).unwrap(), vec![false]);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn poll_readable_rejects_invalid_fds() {
+        // File descriptors aren't allocated this high, so it's never open.
+        let fd = ::libc::c_int::MAX;
+
+        let err = poll_readable(&[fd], Some(Duration::from_secs(0))).unwrap_err();
+        assert_eq!(::error::raw_os_error(&err), ::libc::EBADF);
+    }
+}